@@ -1,16 +1,27 @@
+pub mod dry_run;
 pub mod local;
 pub mod remote;
+#[cfg(feature = "test-validator")]
+pub mod test_validator;
 
 use std::error::Error;
 
+pub use dry_run::DryRunClientSync;
 pub use local::LocalClientSync;
 use solana_sdk::{
     account::{from_account, Account},
     hash::Hash,
+    instruction::{CompiledInstruction, Instruction, InstructionError},
+    message::Message,
+    packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
+    signature::Signature,
     sysvar::Sysvar,
     transaction::{Transaction, TransactionError},
 };
+use spl_token::instruction::TokenInstruction;
+#[cfg(feature = "test-validator")]
+pub use test_validator::TestValidatorClientSync;
 use thiserror::Error;
 
 /// Generalized struct to represent the essence of
@@ -22,6 +33,121 @@ pub struct TransactionDetails {
     /// Consumed amount of computation unit.
     /// Might be `None` for successfully executed remote transactions.
     pub units_consumed: Option<u64>,
+    /// Instructions invoked through cross-program invocations, in the order of execution.
+    /// Might be empty for transactions that failed the preflight check of remote clients.
+    pub inner_instructions: Vec<InnerInstruction>,
+    /// Lamports collected as rent from the loaded accounts, which are not caused by the instructions.
+    /// Only reported by the local client.
+    pub rent_collections: Vec<(Pubkey, u64)>,
+}
+
+impl TransactionDetails {
+    /// Returns the cross-program invocations of `program_id`.
+    pub fn inner_instructions_of(
+        &self,
+        program_id: Pubkey,
+    ) -> impl Iterator<Item = &InnerInstruction> {
+        self.inner_instructions
+            .iter()
+            .filter(move |instruction| instruction.program_id == program_id)
+    }
+
+    /// Returns the lamports collected as rent from the account during the transaction.
+    pub fn rent_collected_from(&self, address: Pubkey) -> u64 {
+        self.rent_collections
+            .iter()
+            .filter(|(rent_payer, _)| *rent_payer == address)
+            .map(|(_, lamports)| lamports)
+            .sum()
+    }
+
+    /// Returns whether a program aborted during the execution (e.g., panic, abort,
+    /// or a VM error such as an access violation) instead of cleanly returning an error.
+    ///
+    /// This is determined from the log messages emitted by the runtime.
+    pub fn aborted(&self) -> bool {
+        self.log_messages.iter().any(|log| {
+            log.contains("Program failed to complete") || log.contains("program panicked")
+        })
+    }
+
+    /// Returns the deepest invocation stack height reached during the execution.
+    ///
+    /// This is computed from the `Program <id> invoke [<depth>]` log messages,
+    /// where top-level instructions have a depth of 1.
+    /// Returns 0 if no invocation was logged, e.g., when the logs are truncated.
+    pub fn max_cpi_depth(&self) -> u32 {
+        self.log_messages
+            .iter()
+            .filter_map(|log| {
+                let depth = log
+                    .strip_prefix("Program ")?
+                    .split_once(" invoke [")?
+                    .1
+                    .strip_suffix(']')?;
+                depth.parse().ok()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Panics if the invocation stack height exceeded `max_depth` during the execution.
+    ///
+    /// See [Self::max_cpi_depth] for how the depth is counted.
+    pub fn assert_max_cpi_depth(&self, max_depth: u32) {
+        let depth = self.max_cpi_depth();
+        assert!(
+            depth <= max_depth,
+            "Invocation depth {} exceeds the maximum of {}",
+            depth,
+            max_depth
+        );
+    }
+}
+
+/// An instruction invoked through a cross-program invocation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InnerInstruction {
+    /// Index of the top-level instruction that made this invocation.
+    pub index: u8,
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+}
+
+impl InnerInstruction {
+    /// Decodes a compiled instruction against the account keys of the transaction message.
+    pub(crate) fn from_compiled(
+        index: u8,
+        instruction: &CompiledInstruction,
+        account_keys: &[Pubkey],
+    ) -> Self {
+        InnerInstruction {
+            index,
+            program_id: account_keys[instruction.program_id_index as usize],
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|&account_index| account_keys[account_index as usize])
+                .collect(),
+            data: instruction.data.clone(),
+        }
+    }
+
+    /// Decodes the instruction data if this is an invocation of the SPL token program.
+    pub fn token_instruction(&self) -> Option<TokenInstruction> {
+        if self.program_id == spl_token::ID {
+            decode_token_instruction(&self.data)
+        } else {
+            None
+        }
+    }
+}
+
+/// Decodes the data of an SPL token instruction.
+/// Returns `None` if the data is not a valid token instruction.
+pub fn decode_token_instruction(data: &[u8]) -> Option<TokenInstruction> {
+    TokenInstruction::unpack(data).ok()
 }
 
 #[derive(Debug, Error)]
@@ -43,6 +169,58 @@ pub enum ClientError<E: Error> {
     AccountNotFound(Pubkey),
     #[error("account {} contains invalid data that cannot be deserialized", 0)]
     InvalidAccountData(Pubkey),
+    /// The client did not report the number of consumed compute units.
+    #[error("consumed compute units are not available from the client")]
+    UnitsConsumedUnavailable,
+    #[error("account data of {size} bytes exceeds the maximum of {max} bytes")]
+    AccountDataTooLarge { size: usize, max: usize },
+    #[error("account {0} does not contain the expected data")]
+    AccountDataMismatch(Pubkey),
+    #[error("account {0} is not rent-exempt")]
+    NotRentExempt(Pubkey),
+    #[error("timed out")]
+    Timeout,
+    /// The remote environment was built without a payer.
+    #[error("payer is not specified for the remote environment")]
+    PayerNotSpecified,
+    /// The total balance of the token accounts does not match the supply of the mint.
+    #[error("mint {mint} has a supply of {supply}, but the token accounts hold {total} in total")]
+    SupplyMismatch {
+        mint: Pubkey,
+        supply: u64,
+        total: u128,
+    },
+    /// The account is not owned by the expected program.
+    #[error("account {address} is owned by {actual}, expected {expected}")]
+    WrongOwner {
+        address: Pubkey,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+    /// The destination did not receive exactly the lamports reclaimed from a closed account.
+    #[error("account {destination} received {actual} lamports, expected {expected}")]
+    RentNotReclaimed {
+        destination: Pubkey,
+        expected: u64,
+        actual: u64,
+    },
+    /// The remote environment is connected to a cluster forbidden with
+    /// `EnvironmentGenesis::forbid_cluster`.
+    #[error("connected to a forbidden cluster with genesis hash {0}")]
+    ForbiddenCluster(Hash),
+    /// The serialized transaction does not fit in a single packet.
+    /// This is detected before the transaction is sent.
+    #[error(
+        "transaction of {size} bytes exceeds the packet limit of {} bytes",
+        PACKET_DATA_SIZE
+    )]
+    TransactionTooLarge { size: usize },
+    /// The account to reallocate is also the payer of the reallocation.
+    #[error("account {0} cannot fund its own reallocation")]
+    SelfFundedReallocation(Pubkey),
+    /// An option set in `EnvironmentGenesis` cannot be applied to the client.
+    #[error("{0} is not supported by this client")]
+    UnsupportedOption(&'static str),
 }
 
 /// An opaque error type that can be used to handle errors from different
@@ -68,6 +246,58 @@ pub enum DynClientError {
     AccountNotFound(Pubkey),
     #[error("account {} contains invalid data that cannot be deserialized", 0)]
     InvalidAccountData(Pubkey),
+    /// The client did not report the number of consumed compute units.
+    #[error("consumed compute units are not available from the client")]
+    UnitsConsumedUnavailable,
+    #[error("account data of {size} bytes exceeds the maximum of {max} bytes")]
+    AccountDataTooLarge { size: usize, max: usize },
+    #[error("account {0} does not contain the expected data")]
+    AccountDataMismatch(Pubkey),
+    #[error("account {0} is not rent-exempt")]
+    NotRentExempt(Pubkey),
+    #[error("timed out")]
+    Timeout,
+    /// The remote environment was built without a payer.
+    #[error("payer is not specified for the remote environment")]
+    PayerNotSpecified,
+    /// The total balance of the token accounts does not match the supply of the mint.
+    #[error("mint {mint} has a supply of {supply}, but the token accounts hold {total} in total")]
+    SupplyMismatch {
+        mint: Pubkey,
+        supply: u64,
+        total: u128,
+    },
+    /// The account is not owned by the expected program.
+    #[error("account {address} is owned by {actual}, expected {expected}")]
+    WrongOwner {
+        address: Pubkey,
+        expected: Pubkey,
+        actual: Pubkey,
+    },
+    /// The destination did not receive exactly the lamports reclaimed from a closed account.
+    #[error("account {destination} received {actual} lamports, expected {expected}")]
+    RentNotReclaimed {
+        destination: Pubkey,
+        expected: u64,
+        actual: u64,
+    },
+    /// The remote environment is connected to a cluster forbidden with
+    /// `EnvironmentGenesis::forbid_cluster`.
+    #[error("connected to a forbidden cluster with genesis hash {0}")]
+    ForbiddenCluster(Hash),
+    /// The serialized transaction does not fit in a single packet.
+    /// This is detected before the transaction is sent.
+    #[error(
+        "transaction of {size} bytes exceeds the packet limit of {} bytes",
+        PACKET_DATA_SIZE
+    )]
+    TransactionTooLarge { size: usize },
+    /// The account to reallocate is also the payer of the reallocation.
+    #[error("account {0} cannot fund its own reallocation")]
+    SelfFundedReallocation(Pubkey),
+    /// An option set in `EnvironmentGenesis` cannot be applied to the client.
+    #[error("{0} is not supported by this client")]
+    UnsupportedOption(&'static str),
 }
 
 impl<E> From<ClientError<E>> for DynClientError
@@ -83,6 +313,173 @@ where
             }
             ClientError::AccountNotFound(pubkey) => DynClientError::AccountNotFound(pubkey),
             ClientError::InvalidAccountData(pubkey) => DynClientError::InvalidAccountData(pubkey),
+            ClientError::UnitsConsumedUnavailable => DynClientError::UnitsConsumedUnavailable,
+            ClientError::AccountDataTooLarge { size, max } => {
+                DynClientError::AccountDataTooLarge { size, max }
+            }
+            ClientError::AccountDataMismatch(pubkey) => DynClientError::AccountDataMismatch(pubkey),
+            ClientError::NotRentExempt(pubkey) => DynClientError::NotRentExempt(pubkey),
+            ClientError::Timeout => DynClientError::Timeout,
+            ClientError::PayerNotSpecified => DynClientError::PayerNotSpecified,
+            ClientError::SupplyMismatch {
+                mint,
+                supply,
+                total,
+            } => DynClientError::SupplyMismatch {
+                mint,
+                supply,
+                total,
+            },
+            ClientError::WrongOwner {
+                address,
+                expected,
+                actual,
+            } => DynClientError::WrongOwner {
+                address,
+                expected,
+                actual,
+            },
+            ClientError::RentNotReclaimed {
+                destination,
+                expected,
+                actual,
+            } => DynClientError::RentNotReclaimed {
+                destination,
+                expected,
+                actual,
+            },
+            ClientError::ForbiddenCluster(genesis_hash) => {
+                DynClientError::ForbiddenCluster(genesis_hash)
+            }
+            ClientError::TransactionTooLarge { size } => {
+                DynClientError::TransactionTooLarge { size }
+            }
+            ClientError::SelfFundedReallocation(address) => {
+                DynClientError::SelfFundedReallocation(address)
+            }
+            ClientError::UnsupportedOption(option) => DynClientError::UnsupportedOption(option),
+        }
+    }
+}
+
+/// Anchor custom error codes start from this offset.
+#[cfg(feature = "anchor")]
+const ANCHOR_ERROR_CODE_OFFSET: u32 = 6000;
+
+#[cfg(feature = "anchor")]
+fn anchor_error_from_transaction_error<T: TryFrom<u32>>(error: &TransactionError) -> Option<T> {
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code
+            .checked_sub(ANCHOR_ERROR_CODE_OFFSET)
+            .and_then(|index| T::try_from(index).ok()),
+        _ => None,
+    }
+}
+
+/// Finds the instruction that caused `error` among the instructions of the transaction.
+fn failed_instruction_from_transaction_error<'a>(
+    error: &'a TransactionError,
+    instructions: &'a [Instruction],
+) -> Option<(&'a Instruction, &'a InstructionError)> {
+    match error {
+        TransactionError::InstructionError(index, instruction_error) => instructions
+            .get(*index as usize)
+            .map(|instruction| (instruction, instruction_error)),
+        _ => None,
+    }
+}
+
+impl<E: Error> ClientError<E> {
+    /// Returns whether the transaction was rejected because an identical one was already processed.
+    ///
+    /// The local bank rejects it before the execution,
+    /// while RPC nodes may report it as a failed transaction from the preflight simulation.
+    pub(crate) fn is_already_processed(&self) -> bool {
+        matches!(
+            self,
+            ClientError::InvalidTransaction(TransactionError::AlreadyProcessed)
+                | ClientError::FailedTransaction {
+                    error: TransactionError::AlreadyProcessed,
+                    ..
+                }
+        )
+    }
+
+    /// Correlates the index of a failed instruction with `instructions`,
+    /// which should be the instructions submitted in the transaction.
+    /// Returns `None` if the transaction did not fail with an instruction error.
+    pub fn failed_instruction<'a>(
+        &'a self,
+        instructions: &'a [Instruction],
+    ) -> Option<(&'a Instruction, &'a InstructionError)> {
+        match self {
+            ClientError::FailedTransaction { error, .. } => {
+                failed_instruction_from_transaction_error(error, instructions)
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes an Anchor custom error code into the caller's error enum.
+    /// Returns `None` if the transaction did not fail with an Anchor custom error.
+    #[cfg(feature = "anchor")]
+    pub fn anchor_error<T: TryFrom<u32>>(&self) -> Option<T> {
+        match self {
+            ClientError::FailedTransaction { error, .. } => {
+                anchor_error_from_transaction_error(error)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl DynClientError {
+    /// Correlates the index of a failed instruction with `instructions`,
+    /// which should be the instructions submitted in the transaction.
+    /// Returns `None` if the transaction did not fail with an instruction error.
+    pub fn failed_instruction<'a>(
+        &'a self,
+        instructions: &'a [Instruction],
+    ) -> Option<(&'a Instruction, &'a InstructionError)> {
+        match self {
+            DynClientError::FailedTransaction { error, .. } => {
+                failed_instruction_from_transaction_error(error, instructions)
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes an Anchor custom error code into the caller's error enum.
+    /// Returns `None` if the transaction did not fail with an Anchor custom error.
+    #[cfg(feature = "anchor")]
+    pub fn anchor_error<T: TryFrom<u32>>(&self) -> Option<T> {
+        match self {
+            DynClientError::FailedTransaction { error, .. } => {
+                anchor_error_from_transaction_error(error)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A filter for scanning program accounts,
+/// equivalent to the filters of `getProgramAccounts` RPC method.
+#[derive(Clone, Debug)]
+pub enum AccountFilter {
+    /// Matches accounts whose data length is equal to the given size.
+    DataSize(u64),
+    /// Matches accounts whose data contains `bytes` at `offset`.
+    Memcmp { offset: usize, bytes: Vec<u8> },
+}
+
+impl AccountFilter {
+    /// Returns whether the account data satisfies this filter.
+    pub fn matches(&self, data: &[u8]) -> bool {
+        match self {
+            AccountFilter::DataSize(size) => data.len() as u64 == *size,
+            AccountFilter::Memcmp { offset, bytes } => {
+                data.get(*offset..offset + bytes.len()) == Some(bytes.as_slice())
+            }
         }
     }
 }
@@ -95,14 +492,77 @@ pub trait ClientSync {
         transaction: Transaction,
     ) -> Result<TransactionDetails, ClientError<Self::ChannelError>>;
 
+    /// Sends multiple independent transactions and returns their details in order.
+    ///
+    /// The default implementation sends them one by one and stops at the first failure.
+    /// Clients can override this to submit the transactions concurrently.
+    fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionDetails>, ClientError<Self::ChannelError>> {
+        transactions
+            .into_iter()
+            .map(|transaction| self.send_transaction(transaction))
+            .collect()
+    }
+
+    /// Waits until a previously sent transaction is confirmed and returns its details.
+    ///
+    /// Returns `ClientError::InvalidTransaction(TransactionError::SignatureFailure)`
+    /// if the client knows that the signature will never be confirmed.
+    fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>>;
+
     fn latest_blockhash(&mut self) -> Result<Hash, Self::ChannelError>;
 
     fn tick_beyond(&mut self, blockhash: Hash) -> Result<Hash, Self::ChannelError>;
 
+    /// Get the fee that the network charges for the message.
+    fn get_fee_for_message(
+        &mut self,
+        message: &Message,
+    ) -> Result<u64, ClientError<Self::ChannelError>>;
+
+    /// Get the minimum balance for an account with `data_len` bytes of data to be rent-exempt,
+    /// as reported by the network.
+    fn minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError<Self::ChannelError>>;
+
     /// Get account data from the chain.
     /// Returns `Err(ClientError::AccountNotFound(pubkey))` if the target account does not exist.
     fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientError<Self::ChannelError>>;
 
+    /// Get `length` bytes of the account data starting at `offset`.
+    /// The result is truncated if the range exceeds the account data.
+    ///
+    /// The default implementation fetches the whole account and slices the data.
+    fn get_account_data_slice(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, ClientError<Self::ChannelError>> {
+        let account = self.get_account(address)?;
+        Ok(account
+            .data
+            .iter()
+            .skip(offset)
+            .take(length)
+            .copied()
+            .collect())
+    }
+
+    /// Get all accounts owned by `program_id` that satisfy every filter.
+    fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError<Self::ChannelError>>;
+
     fn get_sysvar<T: Sysvar>(&mut self) -> Result<T, ClientError<Self::ChannelError>> {
         self.get_account(T::id()).and_then(|account| {
             from_account::<T, _>(&account).ok_or(ClientError::InvalidAccountData(T::id()))
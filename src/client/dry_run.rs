@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use solana_sdk::{
+    account::Account,
+    hash::{hash, Hash},
+    message::Message,
+    native_token::sol_to_lamports,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+use super::{AccountFilter, ClientError, ClientSync, TransactionDetails};
+use crate::{script, Environment, EnvironmentGenesis};
+
+/// A client that records transactions instead of executing them.
+///
+/// Every transaction succeeds with empty details, and account reads only see
+/// the genesis accounts, which are never modified.
+/// This is useful for reviewing a sequence of transactions without executing anything.
+pub struct DryRunClientSync {
+    accounts: HashMap<Pubkey, Account>,
+    blockhash: Hash,
+    transactions: Vec<Transaction>,
+}
+
+impl DryRunClientSync {
+    pub(crate) fn new(genesis: EnvironmentGenesis) -> Environment<Self> {
+        let rent = Rent::default();
+
+        let payer = match genesis.payer {
+            Some(keypair) => keypair,
+            None => Keypair::new(),
+        };
+
+        let mut accounts: HashMap<_, _> = genesis
+            .accounts
+            .iter()
+            .map(|(&address, account_config)| {
+                (address, account_config.clone().to_account(&rent).into())
+            })
+            .collect();
+        accounts.insert(
+            payer.pubkey(),
+            Account {
+                lamports: sol_to_lamports(1_000_000_000.0),
+                data: Default::default(),
+                owner: system_program::ID,
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+
+        let client = DryRunClientSync {
+            accounts,
+            blockhash: hash(b"sunburn dry run"),
+            transactions: Vec::new(),
+        };
+
+        Environment {
+            client,
+            address_labels: genesis.address_labels,
+            payer,
+            rent,
+            log_config: genesis.log_config.unwrap_or_default(),
+            last_used_blockhash: None,
+            auto_advance_blockhash: genesis.auto_advance_blockhash,
+            batched_writes: genesis.batched_writes,
+            write_chunk_size: genesis.write_chunk_size,
+            payer_balance_warning: genesis.payer_balance_warning,
+            total_units_consumed: 0,
+            transaction_script: script::create_file(genesis.transaction_script.as_deref()),
+            transactions_sent: 0,
+            summary_reporter: Default::default(),
+        }
+    }
+
+    /// Returns the recorded transactions in the order they were sent.
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+}
+
+impl Environment<DryRunClientSync> {
+    /// Returns the transactions recorded by the dry-run client in the order they were sent.
+    pub fn recorded_transactions(&self) -> &[Transaction] {
+        self.client.transactions()
+    }
+}
+
+impl ClientSync for DryRunClientSync {
+    // Switch to ! type when it is stabilized
+    type ChannelError = std::convert::Infallible;
+
+    fn send_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        self.transactions.push(transaction);
+        Ok(TransactionDetails {
+            log_messages: Vec::new(),
+            units_consumed: None,
+            inner_instructions: Vec::new(),
+            rent_collections: Vec::new(),
+        })
+    }
+
+    fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        if self
+            .transactions
+            .iter()
+            .any(|transaction| transaction.signatures.first() == Some(&signature))
+        {
+            Ok(TransactionDetails {
+                log_messages: Vec::new(),
+                units_consumed: None,
+                inner_instructions: Vec::new(),
+                rent_collections: Vec::new(),
+            })
+        } else {
+            Err(ClientError::InvalidTransaction(
+                TransactionError::SignatureFailure,
+            ))
+        }
+    }
+
+    fn latest_blockhash(&mut self) -> Result<Hash, Self::ChannelError> {
+        Ok(self.blockhash)
+    }
+
+    fn tick_beyond(&mut self, blockhash: Hash) -> Result<Hash, Self::ChannelError> {
+        if self.blockhash == blockhash {
+            self.blockhash = hash(self.blockhash.as_ref());
+        }
+        Ok(self.blockhash)
+    }
+
+    fn get_fee_for_message(
+        &mut self,
+        _message: &Message,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        Ok(0)
+    }
+
+    fn minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        Ok(Rent::default().minimum_balance(data_len))
+    }
+
+    fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientError<Self::ChannelError>> {
+        self.accounts
+            .get(&address)
+            .cloned()
+            .ok_or(ClientError::AccountNotFound(address))
+    }
+
+    fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError<Self::ChannelError>> {
+        Ok(self
+            .accounts
+            .iter()
+            .filter(|(_, account)| {
+                account.owner == program_id
+                    && filters.iter().all(|filter| filter.matches(&account.data))
+            })
+            .map(|(address, account)| (*address, account.clone()))
+            .collect())
+    }
+}
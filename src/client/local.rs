@@ -1,22 +1,47 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use solana_runtime::bank::{Bank, TransactionExecutionResult};
+use solana_client::{client_error::ClientError as SolanaClientError, rpc_client::RpcClient};
+use solana_runtime::{
+    accounts_index::ScanConfig,
+    bank::{Bank, RentDebits, TransactionExecutionResult},
+};
 use solana_sdk::{
-    account::Account,
-    clock::MAX_PROCESSING_AGE,
+    account::{from_account, Account, AccountSharedData, ReadableAccount, WritableAccount},
+    account_utils::StateMut,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    clock::{Slot, MAX_PROCESSING_AGE},
+    feature::{self, Feature},
     genesis_config::GenesisConfig,
     hash::Hash,
+    message::{Message, SanitizedMessage},
+    native_loader,
     native_token::sol_to_lamports,
+    program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
-    signature::Keypair,
+    signature::{Keypair, Signature},
     signer::Signer,
+    system_instruction::MAX_PERMITTED_DATA_LENGTH,
     system_program,
-    transaction::{Transaction, VersionedTransaction},
+    sysvar::{self, SysvarId},
+    transaction::{Transaction, TransactionError, VersionedTransaction},
 };
+use solana_transaction_status::UiTransactionEncoding;
+use spl_token::state::AccountState;
 
-use super::{ClientError, ClientSync, TransactionDetails};
-use crate::{Environment, EnvironmentGenesis, LogConfig};
+use super::{
+    remote::convert_error, AccountFilter, ClientError, ClientSync, InnerInstruction,
+    TransactionDetails,
+};
+use crate::{json::JsonAccountEntry, script, Environment, EnvironmentGenesis, Loader, LogConfig};
 
 pub(crate) static INTERNAL_LOGGING: AtomicBool = AtomicBool::new(false);
 
@@ -30,15 +55,70 @@ pub fn disable_internal_logging() {
     LogConfig::update_logger();
 }
 
+/// A client backed by an in-process bank.
+///
+/// All transactions are processed in a single bank,
+/// so the `Clock` sysvar does not change across transactions
+/// unless the bank is explicitly advanced with [Environment::warp_to_slot].
+/// New blockhashes are produced by registering ticks to the same bank.
 pub struct LocalClientSync {
-    bank: Bank,
+    bank: Arc<Bank>,
+    /// Genesis of the bank, kept to rebuild the bank on reset.
+    /// `None` if the bank was built externally.
+    genesis_config: Option<GenesisConfig>,
+    loaders: Vec<Loader>,
+    initial_slot: Slot,
+    account_write_hook: Option<AccountWriteHook>,
+    /// Details of the most recently executed transactions, oldest first.
+    recent_details: VecDeque<TransactionDetails>,
+    recent_details_capacity: usize,
 }
 
-impl LocalClientSync {
-    pub(crate) fn new(genesis: EnvironmentGenesis) -> Environment<Self> {
+/// A callback invoked with the address and the states before and after each account write.
+/// `None` represents an account that does not exist.
+pub type AccountWriteHook = Box<dyn FnMut(Pubkey, Option<&Account>, Option<&Account>)>;
+
+/// A transaction recorded on a cluster and replayed in the local bank.
+#[derive(Debug)]
+pub struct ReplayedTransaction {
+    /// Error of the transaction recorded on the cluster, `None` if it succeeded.
+    pub original_error: Option<TransactionError>,
+    /// Result of the replay in the local bank.
+    pub replayed: Result<TransactionDetails, ClientError<std::convert::Infallible>>,
+}
+
+impl ReplayedTransaction {
+    /// Returns whether the replay produced a different result from the cluster.
+    pub fn diverged(&self) -> bool {
+        let replayed_error = match &self.replayed {
+            Ok(_) => None,
+            Err(ClientError::FailedTransaction { error, .. })
+            | Err(ClientError::InvalidTransaction(error)) => Some(error),
+            Err(_) => return true,
+        };
+        self.original_error.as_ref() != replayed_error
+    }
+}
+
+/// A local genesis that is prepared once and builds many environments.
+///
+/// Building a template converts the genesis accounts, including the SPL programs,
+/// and the activated features into a genesis config only once,
+/// so each [build](LocalTemplate::build) only creates a fresh bank from it.
+/// This reduces the startup cost of test suites that build the same environment for every test.
+/// Every environment built from a template shares the same payer keypair.
+pub struct LocalTemplate {
+    genesis: EnvironmentGenesis,
+    genesis_config: GenesisConfig,
+    payer: Keypair,
+    rent: Rent,
+}
+
+impl LocalTemplate {
+    pub(crate) fn new(mut genesis: EnvironmentGenesis) -> Self {
         let rent = Rent::default();
 
-        let payer = match genesis.payer {
+        let payer = match genesis.payer.take() {
             Some(keypair) => keypair,
             None => Keypair::new(),
         };
@@ -61,40 +141,584 @@ impl LocalClientSync {
             .into(),
         ));
 
-        let genesis_config = GenesisConfig::new(&accounts, &[]);
+        // Features are activated by the existence of feature accounts at the genesis
+        for feature_id in &genesis.activated_features {
+            accounts.push((
+                *feature_id,
+                feature::create_account(
+                    &Feature {
+                        activated_at: Some(0),
+                    },
+                    rent.minimum_balance(Feature::size_of()),
+                ),
+            ));
+        }
 
-        let mut bank = Bank::new_for_tests(&genesis_config);
+        let mut genesis_config = GenesisConfig::new(&accounts, &[]);
+        genesis_config.fee_rate_governor = genesis.fee_rate_governor();
 
-        // Add loaders
-        macro_rules! add_builtin {
-            ($b:expr) => {
-                bank.add_builtin(&$b.0, &$b.1, $b.2)
-            };
+        LocalTemplate {
+            genesis,
+            genesis_config,
+            payer,
+            rent,
         }
-        add_builtin!(solana_bpf_loader_program::solana_bpf_loader_deprecated_program!());
-        add_builtin!(solana_bpf_loader_program::solana_bpf_loader_program!());
-        add_builtin!(solana_bpf_loader_program::solana_bpf_loader_upgradeable_program!());
+    }
+
+    /// Builds a new local environment with a fresh bank.
+    pub fn build(&self) -> Environment<LocalClientSync> {
+        let genesis = &self.genesis;
 
-        let client = LocalClientSync { bank };
+        let bank =
+            LocalClientSync::new_bank(&self.genesis_config, &genesis.loaders, genesis.initial_slot);
+        let client = LocalClientSync {
+            bank,
+            genesis_config: Some(self.genesis_config.clone()),
+            loaders: genesis.loaders.clone(),
+            initial_slot: genesis.initial_slot,
+            account_write_hook: None,
+            recent_details: VecDeque::new(),
+            recent_details_capacity: genesis.recent_logs_capacity,
+        };
 
         Environment {
             client,
-            _address_labels: genesis.address_labels,
+            address_labels: genesis.address_labels.clone(),
+            payer: Keypair::from_bytes(&self.payer.to_bytes())
+                .expect("Payer keypair should be valid"),
+            rent: self.rent,
+            log_config: genesis.log_config.unwrap_or_default(),
+            last_used_blockhash: None,
+            auto_advance_blockhash: genesis.auto_advance_blockhash,
+            batched_writes: genesis.batched_writes,
+            write_chunk_size: genesis.write_chunk_size,
+            payer_balance_warning: genesis.payer_balance_warning,
+            total_units_consumed: 0,
+            transaction_script: script::create_file(genesis.transaction_script.as_deref()),
+            transactions_sent: 0,
+            summary_reporter: Default::default(),
+        }
+    }
+}
+
+impl LocalClientSync {
+    pub(crate) fn new(genesis: EnvironmentGenesis) -> Environment<Self> {
+        LocalTemplate::new(genesis).build()
+    }
+
+    /// Builds an environment that adopts an externally configured bank.
+    ///
+    /// The payer account should already exist in the bank,
+    /// and the rent is read from the rent sysvar of the bank.
+    /// Other settings follow the defaults of [EnvironmentGenesis].
+    /// The environment cannot be [reset](Environment::reset) because the genesis is unknown.
+    pub fn from_bank(bank: Bank, payer: Keypair) -> Environment<Self> {
+        let rent = bank
+            .get_account(&Rent::id())
+            .and_then(|account| from_account::<Rent, _>(&account))
+            .expect("Bank should have a valid rent sysvar");
+
+        let genesis = EnvironmentGenesis::default();
+        LogConfig::update_logger();
+
+        Environment {
+            client: LocalClientSync {
+                bank: Arc::new(bank),
+                genesis_config: None,
+                loaders: Vec::new(),
+                initial_slot: 0,
+                account_write_hook: None,
+                recent_details: VecDeque::new(),
+                recent_details_capacity: genesis.recent_logs_capacity,
+            },
+            address_labels: genesis.address_labels,
             payer,
             rent,
-            log_config: genesis.log_config.unwrap_or_default(),
+            log_config: LogConfig::default(),
+            last_used_blockhash: None,
+            auto_advance_blockhash: genesis.auto_advance_blockhash,
+            batched_writes: genesis.batched_writes,
+            write_chunk_size: genesis.write_chunk_size,
+            payer_balance_warning: genesis.payer_balance_warning,
+            total_units_consumed: 0,
+            transaction_script: None,
+            transactions_sent: 0,
+            summary_reporter: Default::default(),
+        }
+    }
+
+    fn new_bank(
+        genesis_config: &GenesisConfig,
+        loaders: &[Loader],
+        initial_slot: Slot,
+    ) -> Arc<Bank> {
+        let mut bank = Bank::new_for_tests(genesis_config);
+
+        // Add loaders
+        for (name, program_id, entrypoint) in loaders {
+            bank.add_builtin(name, program_id, *entrypoint);
+        }
+
+        let bank = Arc::new(bank);
+        if initial_slot == 0 {
+            bank
+        } else {
+            Self::warp_bank(bank, initial_slot)
+        }
+    }
+
+    /// Creates a child bank of `bank` at `slot`.
+    ///
+    /// A child bank updates the sysvars such as Clock and SlotHashes for the new slot.
+    /// An intermediate bank is created at each epoch boundary in between,
+    /// so that every epoch transition is recorded in StakeHistory.
+    fn warp_bank(mut bank: Arc<Bank>, slot: Slot) -> Arc<Bank> {
+        let epoch_schedule = bank.epoch_schedule().clone();
+        let target_epoch = epoch_schedule.get_epoch(slot);
+        while bank.epoch() + 1 < target_epoch {
+            let boundary_slot = epoch_schedule.get_first_slot_in_epoch(bank.epoch() + 1);
+            bank = Arc::new(Bank::new_from_parent(
+                &bank,
+                &Pubkey::default(),
+                boundary_slot,
+            ));
+        }
+        Arc::new(Bank::new_from_parent(&bank, &Pubkey::default(), slot))
+    }
+
+    /// Dumps the accounts at the given addresses in the format of `solana account --output json`.
+    /// Accounts that do not exist are skipped.
+    pub fn dump_accounts(&self, addresses: &[Pubkey]) -> serde_json::Value {
+        serde_json::Value::Array(
+            self.existing_accounts(addresses)
+                .map(|(address, account)| {
+                    serde_json::to_value(JsonAccountEntry::from_account(address, &account))
+                        .expect("Account should be serializable")
+                })
+                .collect(),
+        )
+    }
+
+    /// Writes the accounts at the given addresses to `<dir>/<address>.json`
+    /// in the format of `solana account --output json`.
+    /// Accounts that do not exist are skipped.
+    ///
+    /// The directory can be loaded back with [EnvironmentGenesis::add_accounts_from_json].
+    pub fn dump_accounts_to_dir(
+        &self,
+        addresses: &[Pubkey],
+        dir: impl AsRef<Path>,
+    ) -> std::io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        for (address, account) in self.existing_accounts(addresses) {
+            let file = fs::File::create(dir.join(format!("{}.json", address)))?;
+            serde_json::to_writer_pretty(file, &JsonAccountEntry::from_account(address, &account))?;
+        }
+        Ok(())
+    }
+
+    /// Places an upgradeable program directly into the bank, without deployment transactions.
+    ///
+    /// This writes the Program account at `program_id` and its ProgramData account
+    /// as if the program had been deployed at `slot` with the given upgrade authority.
+    /// Returns the address of the ProgramData account.
+    pub fn set_upgradeable_program(
+        &mut self,
+        program_id: Pubkey,
+        authority: Option<Pubkey>,
+        elf_bytes: &[u8],
+        slot: Slot,
+    ) -> Pubkey {
+        let (programdata_address, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID);
+
+        let programdata_len = UpgradeableLoaderState::programdata_len(elf_bytes.len()).unwrap();
+        let mut programdata = AccountSharedData::new_data_with_space(
+            self.bank
+                .get_minimum_balance_for_rent_exemption(programdata_len),
+            &UpgradeableLoaderState::ProgramData {
+                slot,
+                upgrade_authority_address: authority,
+            },
+            programdata_len,
+            &bpf_loader_upgradeable::ID,
+        )
+        .expect("ProgramData state should be serializable");
+        programdata.data_as_mut_slice()
+            [UpgradeableLoaderState::programdata_data_offset().unwrap()..]
+            .copy_from_slice(elf_bytes);
+
+        let program_len = UpgradeableLoaderState::program_len().unwrap();
+        let mut program = AccountSharedData::new_data_with_space(
+            self.bank
+                .get_minimum_balance_for_rent_exemption(program_len),
+            &UpgradeableLoaderState::Program {
+                programdata_address,
+            },
+            program_len,
+            &bpf_loader_upgradeable::ID,
+        )
+        .expect("Program state should be serializable");
+        program.set_executable(true);
+
+        self.bank.store_account(&programdata_address, &programdata);
+        self.bank.store_account(&program_id, &program);
+
+        programdata_address
+    }
+
+    fn record_details(&mut self, details: &TransactionDetails) {
+        if self.recent_details_capacity == 0 {
+            return;
+        }
+        if self.recent_details.len() == self.recent_details_capacity {
+            self.recent_details.pop_front();
+        }
+        self.recent_details.push_back(details.clone());
+    }
+
+    fn existing_accounts<'a>(
+        &'a self,
+        addresses: &'a [Pubkey],
+    ) -> impl Iterator<Item = (Pubkey, Account)> + 'a {
+        addresses.iter().filter_map(|address| {
+            self.bank
+                .get_account(address)
+                .map(|account| (*address, account.into()))
+        })
+    }
+}
+
+impl Environment<LocalClientSync> {
+    /// Rebuilds the bank from the original genesis, discarding every change made since then.
+    ///
+    /// This is cheaper than building a new environment from [EnvironmentGenesis],
+    /// so a single environment can be reused across test cases.
+    /// The payer keypair is kept across resets.
+    ///
+    /// Panics if the environment was built with [LocalClientSync::from_bank].
+    pub fn reset(&mut self) {
+        let genesis_config = self
+            .client
+            .genesis_config
+            .as_ref()
+            .expect("Environment built from an existing bank cannot be reset");
+        self.client.bank = LocalClientSync::new_bank(
+            genesis_config,
+            &self.client.loaders,
+            self.client.initial_slot,
+        );
+        self.last_used_blockhash = None;
+    }
+
+    /// Advances the local bank to `slot` by creating a child bank at the slot.
+    ///
+    /// The sysvars are updated the way the runtime does for a new bank:
+    /// `Clock` and `SlotHashes` reflect the new slot, and crossing an epoch boundary
+    /// updates `StakeHistory` and activates the pending stake warmup and cooldown.
+    /// When the warp crosses several epochs, an intermediate bank is created
+    /// at the first slot of each epoch, so every epoch transition is applied.
+    /// The slots in between are skipped, so `SlotHashes` only contains the visited slots.
+    /// Accounts and the recent blockhashes are carried over to the new bank.
+    ///
+    /// Panics if `slot` is not after the current slot.
+    pub fn warp_to_slot(&mut self, slot: Slot) {
+        let current_slot = self.client.bank.slot();
+        assert!(
+            slot > current_slot,
+            "Cannot warp to slot {} from slot {}",
+            slot,
+            current_slot
+        );
+        self.client.bank = LocalClientSync::warp_bank(self.client.bank.clone(), slot);
+    }
+
+    /// Advances the local bank to the first slot of the next epoch.
+    ///
+    /// See [Self::warp_to_slot] for the sysvars updated by the advance.
+    pub fn advance_epoch(&mut self) {
+        let next_epoch = self.client.bank.epoch() + 1;
+        let slot = self
+            .client
+            .bank
+            .epoch_schedule()
+            .get_first_slot_in_epoch(next_epoch);
+        self.warp_to_slot(slot);
+    }
+
+    /// Registers a callback invoked for each account modified by a transaction.
+    ///
+    /// The accounts of each transaction are compared before and after the execution,
+    /// and the callback receives the address and both states of the modified ones.
+    /// The changes include the rent collected from the loaded accounts,
+    /// which is reported separately in [TransactionDetails::rent_collections].
+    /// This replaces the previously registered callback.
+    pub fn on_account_write(
+        &mut self,
+        hook: impl FnMut(Pubkey, Option<&Account>, Option<&Account>) + 'static,
+    ) {
+        self.client.account_write_hook = Some(Box::new(hook));
+    }
+
+    /// Returns the details of the most recently executed transactions, oldest first.
+    ///
+    /// Failed transactions are included, so the logs can be inspected
+    /// even if the error was not handled by the caller.
+    /// The number of retained entries is set with [EnvironmentGenesis::with_recent_logs_capacity].
+    pub fn recent_logs(&self) -> impl Iterator<Item = &TransactionDetails> {
+        self.client.recent_details.iter()
+    }
+
+    /// Replays a transaction recorded on the cluster at `rpc_url` against the local bank.
+    ///
+    /// The accounts referenced by the transaction, including the ProgramData accounts
+    /// of upgradeable programs, are cloned from the cluster into the bank,
+    /// and the original signed transaction is executed again.
+    /// Builtin programs and sysvars are not cloned.
+    ///
+    /// Note that the accounts are cloned in their current state,
+    /// which may differ from the state when the transaction was originally executed.
+    /// Transactions that use address lookup tables are not supported.
+    pub fn replay_remote_transaction(
+        &mut self,
+        signature: Signature,
+        rpc_url: impl ToString,
+    ) -> Result<ReplayedTransaction, ClientError<SolanaClientError>> {
+        let rpc_client = RpcClient::new(rpc_url.to_string());
+        let transaction_data = rpc_client
+            .get_transaction(&signature, UiTransactionEncoding::Base64)
+            .map_err(convert_error)?;
+
+        let original_error = transaction_data.transaction.meta.and_then(|meta| meta.err);
+        let transaction = transaction_data
+            .transaction
+            .transaction
+            .decode()
+            .and_then(VersionedTransaction::into_legacy_transaction)
+            .ok_or(ClientError::InvalidTransaction(
+                TransactionError::UnsupportedVersion,
+            ))?;
+
+        let mut addresses = transaction.message.account_keys.clone();
+        let mut accounts = rpc_client
+            .get_multiple_accounts(&addresses)
+            .map_err(convert_error)?;
+
+        // Upgradeable programs need their ProgramData accounts to be executed
+        let programdata_addresses: Vec<_> = accounts
+            .iter()
+            .flatten()
+            .filter(|account| account.owner == bpf_loader_upgradeable::ID)
+            .filter_map(|account| match account.state() {
+                Ok(UpgradeableLoaderState::Program {
+                    programdata_address,
+                }) => Some(programdata_address),
+                _ => None,
+            })
+            .collect();
+        accounts.extend(
+            rpc_client
+                .get_multiple_accounts(&programdata_addresses)
+                .map_err(convert_error)?,
+        );
+        addresses.extend(programdata_addresses);
+
+        for (address, account) in addresses.iter().zip(accounts) {
+            if let Some(account) = account {
+                // Builtin programs and sysvars are provided by the local bank
+                if account.owner == native_loader::ID || sysvar::check_id(&account.owner) {
+                    continue;
+                }
+                self.client
+                    .bank
+                    .store_account(address, &AccountSharedData::from(account));
+            }
         }
+
+        // Register the original blockhash so that the signed transaction is accepted
+        let recent_blockhash = transaction.message.recent_blockhash;
+        while self.client.bank.last_blockhash() != recent_blockhash {
+            self.client.bank.register_tick(&recent_blockhash);
+        }
+
+        Ok(ReplayedTransaction {
+            original_error,
+            replayed: self.client.send_transaction(transaction),
+        })
+    }
+
+    /// Replays the transactions recorded with [EnvironmentGenesis::with_transaction_script]
+    /// in order and returns their results.
+    ///
+    /// The recorded transactions are sent as they are with the original signatures,
+    /// after registering their blockhashes to the bank.
+    /// Therefore, the environment should be built from the same genesis as the recording one,
+    /// including the payer keypair, to reproduce the results.
+    pub fn replay_script(
+        &mut self,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<Vec<Result<TransactionDetails, ClientError<std::convert::Infallible>>>>
+    {
+        let transactions = script::read_transactions(path)?;
+        Ok(transactions
+            .into_iter()
+            .map(|transaction| {
+                let recent_blockhash = transaction.message.recent_blockhash;
+                if !self.client.bank.is_blockhash_valid(&recent_blockhash) {
+                    while self.client.bank.last_blockhash() != recent_blockhash {
+                        self.client.bank.register_tick(&recent_blockhash);
+                    }
+                }
+                self.send_transaction(transaction)
+            })
+            .collect())
+    }
+
+    /// Stores a token account of `mint` with the given balance directly into the bank.
+    ///
+    /// An existing account at the address is overwritten,
+    /// and the supply of the mint is adjusted by the difference in the balance
+    /// so that it stays consistent with the token accounts.
+    /// The token account is funded to the rent-exempt minimum.
+    ///
+    /// Returns `ClientError::AccountDataMismatch` if the address holds a token account
+    /// of another mint, whose supply would otherwise go out of sync.
+    pub fn set_token_balance(
+        &mut self,
+        account: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        amount: u64,
+    ) -> Result<(), ClientError<std::convert::Infallible>> {
+        let mut mint_state = self.token_mint(mint)?;
+        let previous_amount = match self.token_account(account) {
+            Ok(token_account) if token_account.mint == mint => token_account.amount,
+            Ok(_) => return Err(ClientError::AccountDataMismatch(account)),
+            Err(_) => 0,
+        };
+        mint_state.supply = mint_state
+            .supply
+            .saturating_sub(previous_amount)
+            .checked_add(amount)
+            .expect("Mint supply should not overflow");
+
+        let mut mint_account = self.client.get_account(mint)?;
+        spl_token::state::Mint::pack(mint_state, &mut mint_account.data)
+            .expect("Mint state should be packable");
+
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                state: AccountState::Initialized,
+                ..Default::default()
+            },
+            &mut data,
+        )
+        .expect("Token account state should be packable");
+        let token_account = Account {
+            lamports: self.rent.minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+            rent_epoch: 0,
+        };
+
+        self.client
+            .bank
+            .store_account(&mint, &AccountSharedData::from(mint_account));
+        self.client
+            .bank
+            .store_account(&account, &AccountSharedData::from(token_account));
+
+        Ok(())
+    }
+
+    /// Resizes the data of the account at the given address, preserving its rent-exempt status.
+    ///
+    /// When the account grows, `payer` funds the additional rent-exempt amount.
+    /// When the account shrinks, the excess rent-exempt amount is refunded to `payer`.
+    /// The new bytes are zero-initialized.
+    ///
+    /// This stores the accounts directly into the bank, so it works regardless of the account owner.
+    /// The account cannot be its own payer.
+    pub fn realloc_account(
+        &mut self,
+        account: Pubkey,
+        new_len: usize,
+        payer: &Keypair,
+    ) -> Result<(), ClientError<std::convert::Infallible>> {
+        if new_len > MAX_PERMITTED_DATA_LENGTH as usize {
+            return Err(ClientError::AccountDataTooLarge {
+                size: new_len,
+                max: MAX_PERMITTED_DATA_LENGTH as usize,
+            });
+        }
+
+        if account == payer.pubkey() {
+            return Err(ClientError::SelfFundedReallocation(account));
+        }
+
+        let mut target = self.client.get_account(account)?;
+        let mut funder = self.client.get_account(payer.pubkey())?;
+
+        let old_minimum = self.rent.minimum_balance(target.data.len());
+        let new_minimum = self.rent.minimum_balance(new_len);
+        if new_minimum > target.lamports {
+            let top_up = new_minimum - target.lamports;
+            funder.lamports = funder
+                .lamports
+                .checked_sub(top_up)
+                .expect("Payer should have enough lamports to fund the reallocation");
+            target.lamports += top_up;
+        } else if new_minimum < old_minimum {
+            // Only refund the rent-exempt amount released by shrinking
+            let refund = (old_minimum - new_minimum).min(target.lamports - new_minimum);
+            target.lamports -= refund;
+            funder.lamports += refund;
+        }
+        target.data.resize(new_len, 0);
+
+        self.client
+            .bank
+            .store_account(&payer.pubkey(), &AccountSharedData::from(funder));
+        self.client
+            .bank
+            .store_account(&account, &AccountSharedData::from(target));
+
+        Ok(())
     }
 }
 
 fn convert_tx_result<E: std::error::Error>(
     tx_result: TransactionExecutionResult,
+    rent_debits: RentDebits,
+    account_keys: &[Pubkey],
 ) -> Result<TransactionDetails, ClientError<E>> {
     match tx_result {
         TransactionExecutionResult::Executed { details, .. } => {
+            let inner_instructions = details
+                .inner_instructions
+                .unwrap_or_default()
+                .iter()
+                .enumerate()
+                .flat_map(|(index, instructions)| {
+                    instructions.iter().map(move |instruction| {
+                        InnerInstruction::from_compiled(index as u8, instruction, account_keys)
+                    })
+                })
+                .collect();
             let details_core = TransactionDetails {
                 log_messages: details.log_messages.unwrap_or(Vec::new()),
                 units_consumed: Some(details.executed_units),
+                inner_instructions,
+                rent_collections: rent_debits
+                    .into_unordered_rewards_iter()
+                    .map(|(address, reward)| (address, reward.lamports.unsigned_abs()))
+                    .collect(),
             };
             match details.status {
                 Ok(()) => Ok(details_core),
@@ -118,6 +742,21 @@ impl ClientSync for LocalClientSync {
         &mut self,
         transaction: Transaction,
     ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        // The bank does not verify signatures on its own,
+        // so they are checked here to match the behavior of a cluster
+        transaction
+            .verify()
+            .map_err(ClientError::InvalidTransaction)?;
+
+        let account_keys = transaction.message.account_keys.clone();
+        let before: Vec<_> = match self.account_write_hook {
+            Some(_) => account_keys
+                .iter()
+                .map(|address| self.bank.get_account(address).map(Account::from))
+                .collect(),
+            None => Vec::new(),
+        };
+
         let txs = vec![VersionedTransaction::from(transaction)];
         let batch = self
             .bank
@@ -128,12 +767,55 @@ impl ClientSync for LocalClientSync {
             &batch,
             MAX_PROCESSING_AGE,
             false,
-            false,
+            true,
             true,
             &mut Default::default(),
         );
 
-        convert_tx_result(tx_result.execution_results.pop().unwrap())
+        drop(batch);
+
+        if let Some(hook) = &mut self.account_write_hook {
+            for (address, before) in account_keys.iter().zip(before) {
+                let after = self.bank.get_account(address).map(Account::from);
+                if before != after {
+                    hook(*address, before.as_ref(), after.as_ref());
+                }
+            }
+        }
+
+        let result = convert_tx_result(
+            tx_result.execution_results.pop().unwrap(),
+            tx_result.rent_debits.pop().unwrap_or_default(),
+            &account_keys,
+        );
+        match &result {
+            Ok(details) | Err(ClientError::FailedTransaction { details, .. }) => {
+                self.record_details(details)
+            }
+            _ => (),
+        }
+        result
+    }
+
+    fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        // Transactions are committed when they are sent, so there is nothing to wait for.
+        // The logs are only available from the result of `send_transaction`.
+        let details = TransactionDetails {
+            log_messages: Vec::new(),
+            units_consumed: None,
+            inner_instructions: Vec::new(),
+            rent_collections: Vec::new(),
+        };
+        match self.bank.get_signature_status(&signature) {
+            Some(Ok(())) => Ok(details),
+            Some(Err(error)) => Err(ClientError::FailedTransaction { error, details }),
+            None => Err(ClientError::InvalidTransaction(
+                TransactionError::SignatureFailure,
+            )),
+        }
     }
 
     fn latest_blockhash(&mut self) -> Result<Hash, Self::ChannelError> {
@@ -147,10 +829,48 @@ impl ClientSync for LocalClientSync {
         Ok(self.bank.last_blockhash())
     }
 
+    fn get_fee_for_message(
+        &mut self,
+        message: &Message,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        let message = SanitizedMessage::try_from(message.clone())
+            .map_err(|_| ClientError::InvalidTransaction(TransactionError::SanitizeFailure))?;
+        self.bank
+            .get_fee_for_message(&message)
+            .ok_or(ClientError::InvalidTransaction(
+                TransactionError::BlockhashNotFound,
+            ))
+    }
+
+    fn minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        Ok(self.bank.get_minimum_balance_for_rent_exemption(data_len))
+    }
+
     fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientError<Self::ChannelError>> {
         self.bank
             .get_account(&address)
             .map(|account| account.into())
             .ok_or(ClientError::AccountNotFound(address))
     }
+
+    fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError<Self::ChannelError>> {
+        Ok(self
+            .bank
+            .get_filtered_program_accounts(
+                &program_id,
+                |account| filters.iter().all(|filter| filter.matches(account.data())),
+                &ScanConfig::default(),
+            )
+            .expect("Scan of the local bank should not be aborted")
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect())
+    }
 }
@@ -1,42 +1,196 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    io,
+    time::{Duration, Instant},
+};
 
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
 use solana_client::{
     client_error::{ClientError as SolanaClientError, ClientErrorKind as SolanaClientErrorKind},
     rpc_client::RpcClient,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig,
+        RpcTransactionConfig,
+    },
     rpc_custom_error::{
         JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
         JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE,
     },
-    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    rpc_request::{RpcError, RpcResponseErrorData, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS},
 };
 use solana_sdk::{
     account::Account,
     account_info::IntoAccountInfo,
+    bs58,
+    clock::Slot,
     commitment_config::CommitmentConfig,
     hash::Hash,
+    message::{Message, VersionedMessage},
     pubkey::Pubkey,
     rent::Rent,
+    signature::Signature,
     sysvar::{Sysvar, SysvarId},
     transaction::{Transaction, TransactionError},
 };
-use solana_transaction_status::UiTransactionEncoding;
+use solana_transaction_status::{UiInstruction, UiTransactionEncoding};
 
-use super::{ClientError, ClientSync, TransactionDetails};
-use crate::{Environment, EnvironmentGenesis};
+use super::{AccountFilter, ClientError, ClientSync, InnerInstruction, TransactionDetails};
+use crate::{script, Environment, EnvironmentGenesis};
 
 pub struct RemoteClientSync {
     client: RpcClient,
+    /// Commitment level used for account reads.
+    commitment: CommitmentConfig,
+    /// Commitment level used for transaction confirmation.
+    confirm_commitment: CommitmentConfig,
+    /// Maximum duration to wait for a transaction confirmation.
+    timeout: Option<Duration>,
 }
 
-/// Wrapper around `RpcClient::get_account_with_commitment`.
+/// Wrapper around `RpcClient::get_account_with_commitment`
+/// that returns `ClientError::AccountNotFound` if the account at `pubkey` does not exist.
 fn get_existing_account(
     client: &RpcClient,
     pubkey: &Pubkey,
+    commitment: CommitmentConfig,
 ) -> Result<Account, ClientError<SolanaClientError>> {
     client
-        .get_account_with_commitment(&pubkey, CommitmentConfig::finalized())?
+        .get_account_with_commitment(pubkey, commitment)
+        .map_err(convert_error)?
         .value
-        .ok_or(ClientError::AccountNotFound(pubkey.clone()))
+        .ok_or(ClientError::AccountNotFound(*pubkey))
+}
+
+/// Wrapper around `RpcClient::send_transaction_with_config`
+/// that runs the preflight simulation at `commitment` instead of the commitment of the RPC client.
+fn send_with_preflight_commitment(
+    client: &RpcClient,
+    transaction: &Transaction,
+    commitment: CommitmentConfig,
+) -> Result<Signature, SolanaClientError> {
+    client.send_transaction_with_config(
+        transaction,
+        RpcSendTransactionConfig {
+            preflight_commitment: Some(commitment.commitment),
+            ..RpcSendTransactionConfig::default()
+        },
+    )
+}
+
+/// Fetches the details of a confirmed transaction.
+fn get_transaction_details(
+    client: &RpcClient,
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Result<TransactionDetails, ClientError<SolanaClientError>> {
+    // The transaction is fetched at the commitment it was confirmed with,
+    // since it may not have reached the read commitment yet
+    let transaction_data = client
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(commitment),
+                ..Default::default()
+            },
+        )
+        .map_err(convert_error)?;
+
+    // Account keys of the message are used to decode inner instructions
+    let account_keys = match transaction_data.transaction.transaction.decode() {
+        Some(transaction) => match transaction.message {
+            VersionedMessage::Legacy(message) => message.account_keys,
+            VersionedMessage::V0(message) => message.account_keys,
+        },
+        None => Vec::new(),
+    };
+    let account_keys = &account_keys;
+
+    // FIXME: Investigate if we ever get `None` case here
+    let transaction_meta = transaction_data.transaction.meta.unwrap();
+    let details = TransactionDetails {
+        log_messages: transaction_meta.log_messages.unwrap_or_default(),
+        // `UiTransactionStatusMeta` does not return # of units consumed
+        units_consumed: None,
+        inner_instructions: transaction_meta
+            .inner_instructions
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|inner_instructions| {
+                inner_instructions
+                    .instructions
+                    .iter()
+                    .filter_map(move |instruction| match instruction {
+                        // Instructions referring to unknown account keys
+                        // (e.g., from address lookup tables) are skipped
+                        UiInstruction::Compiled(instruction) => Some(InnerInstruction {
+                            index: inner_instructions.index,
+                            program_id: *account_keys.get(instruction.program_id_index as usize)?,
+                            accounts: instruction
+                                .accounts
+                                .iter()
+                                .map(|&account_index| {
+                                    account_keys.get(account_index as usize).copied()
+                                })
+                                .collect::<Option<_>>()?,
+                            data: bs58::decode(&instruction.data)
+                                .into_vec()
+                                .unwrap_or_default(),
+                        }),
+                        // Only returned for `jsonParsed` encoding
+                        UiInstruction::Parsed(_) => None,
+                    })
+            })
+            .collect(),
+        // Rent collections are reported as rewards, which are not decoded here
+        rent_collections: Vec::new(),
+    };
+
+    match transaction_meta.err {
+        None => Ok(details),
+        Some(error) => Err(ClientError::FailedTransaction { error, details }),
+    }
+}
+
+/// Translates back RPC failure into simulation failure.
+fn convert_send_error(mut err: SolanaClientError) -> ClientError<SolanaClientError> {
+    if let SolanaClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. }) =
+        &mut err.kind
+    {
+        if *code == JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE {
+            return ClientError::InvalidTransaction(TransactionError::SignatureFailure);
+        } else if *code == JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE {
+            if let RpcResponseErrorData::SendTransactionPreflightFailure(simulation_result) = data {
+                return ClientError::FailedTransaction {
+                    error: simulation_result.err.take().unwrap(),
+                    details: TransactionDetails {
+                        log_messages: simulation_result.logs.take().unwrap_or_default(),
+                        units_consumed: simulation_result.units_consumed.take(),
+                        inner_instructions: Vec::new(),
+                        rent_collections: Vec::new(),
+                    },
+                };
+            }
+        }
+    }
+
+    convert_error(err)
+}
+
+/// Converts an RPC client error into [ClientError], mapping request timeouts to `ClientError::Timeout`.
+pub(super) fn convert_error(err: SolanaClientError) -> ClientError<SolanaClientError> {
+    let timed_out = match &err.kind {
+        SolanaClientErrorKind::Reqwest(err) => err.is_timeout(),
+        SolanaClientErrorKind::Io(err) => err.kind() == io::ErrorKind::TimedOut,
+        _ => false,
+    };
+
+    if timed_out {
+        ClientError::Timeout
+    } else {
+        err.into()
+    }
 }
 
 impl RemoteClientSync {
@@ -44,110 +198,404 @@ impl RemoteClientSync {
         genesis: EnvironmentGenesis,
         url: String,
     ) -> Result<Environment<Self>, ClientError<SolanaClientError>> {
-        let client = RpcClient::new(url);
-        let mut rent_account_pair = (Rent::id(), get_existing_account(&client, &Rent::id())?);
+        Self::new_wrapped(genesis, url, |client| client)
+    }
+
+    /// Builds an environment whose client is the remote client wrapped by `wrap`.
+    pub(crate) fn new_wrapped<C: ClientSync>(
+        genesis: EnvironmentGenesis,
+        url: String,
+        wrap: impl FnOnce(Self) -> C,
+    ) -> Result<Environment<C>, ClientError<SolanaClientError>> {
+        let commitment = genesis
+            .commitment
+            .unwrap_or_else(CommitmentConfig::finalized);
+        let client = RpcClient::new_with_commitment(url, commitment);
+        Self::with_client(genesis, client, wrap)
+    }
+
+    pub(crate) fn with_client<C: ClientSync>(
+        genesis: EnvironmentGenesis,
+        client: RpcClient,
+        wrap: impl FnOnce(Self) -> C,
+    ) -> Result<Environment<C>, ClientError<SolanaClientError>> {
+        let payer = genesis.payer.ok_or(ClientError::PayerNotSpecified)?;
+
+        if !genesis.forbidden_clusters.is_empty() {
+            let genesis_hash = client.get_genesis_hash().map_err(convert_error)?;
+            if genesis.forbidden_clusters.contains(&genesis_hash) {
+                return Err(ClientError::ForbiddenCluster(genesis_hash));
+            }
+        }
+
+        let commitment = genesis
+            .commitment
+            .unwrap_or_else(CommitmentConfig::finalized);
+        let mut rent_account_pair = (
+            Rent::id(),
+            get_existing_account(&client, &Rent::id(), commitment)?,
+        );
         let rent = Rent::from_account_info(&rent_account_pair.into_account_info())
             .map_err(|_| ClientError::InvalidAccountData(Rent::id()))?;
 
-        for account_key in genesis.accounts().keys() {
+        for account_key in genesis.accounts.keys() {
             // asserts existence of accounts defined in `EnvironmentGenesis`
-            get_existing_account(&client, account_key)?;
+            get_existing_account(&client, account_key, commitment)?;
         }
 
-        let payer = genesis
-            .payer
-            .expect("Payer should be specified for remote client");
+        // `getTransaction` rejects commitment levels below `confirmed`,
+        // so transactions are confirmed at least at `confirmed` to fetch their details
+        let confirm_commitment = match genesis.confirm_commitment.unwrap_or(commitment) {
+            commitment if commitment.is_at_least_confirmed() => commitment,
+            _ => CommitmentConfig::confirmed(),
+        };
 
         // promote RpcClient into RemoteClientSync
-        let client = RemoteClientSync { client };
+        let client = RemoteClientSync {
+            client,
+            commitment,
+            confirm_commitment,
+            timeout: genesis.timeout,
+        };
 
         Ok(Environment {
-            client,
-            _address_labels: genesis.address_labels,
+            client: wrap(client),
+            address_labels: genesis.address_labels,
             payer,
             rent,
             log_config: genesis.log_config.unwrap_or_default(),
+            last_used_blockhash: None,
+            auto_advance_blockhash: genesis.auto_advance_blockhash,
+            batched_writes: genesis.batched_writes,
+            write_chunk_size: genesis.write_chunk_size,
+            payer_balance_warning: genesis.payer_balance_warning,
+            total_units_consumed: 0,
+            transaction_script: script::create_file(genesis.transaction_script.as_deref()),
+            transactions_sent: 0,
+            summary_reporter: Default::default(),
         })
     }
-}
 
-impl ClientSync for RemoteClientSync {
-    type ChannelError = SolanaClientError;
+    /// Returns the underlying RPC client.
+    pub fn rpc_client(&self) -> &RpcClient {
+        &self.client
+    }
 
-    fn send_transaction(
-        &mut self,
-        transaction: Transaction,
-    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
-        let result = self.client.send_and_confirm_transaction(&transaction);
+    /// Returns the genesis hash of the connected cluster, which identifies the cluster.
+    pub fn genesis_hash(&self) -> Result<Hash, ClientError<SolanaClientError>> {
+        self.client.get_genesis_hash().map_err(convert_error)
+    }
 
-        // Translate back RPC failure into simulation failure
-        match result {
-            Ok(signature) => {
-                let transaction_data = self
-                    .client
-                    .get_transaction(&signature, UiTransactionEncoding::Base64)?;
-
-                // FIXME: Investigate if we ever get `None` case here
-                let transaction_meta = transaction_data.transaction.meta.unwrap();
-                let details = TransactionDetails {
-                    log_messages: transaction_meta.log_messages.unwrap_or_default(),
-                    // `UiTransactionStatusMeta` does not return # of units consumed
-                    units_consumed: None,
-                };
+    /// Returns the commitment level used by this client for account reads.
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.commitment
+    }
+
+    /// Returns the commitment level used by this client for transaction confirmation.
+    ///
+    /// This is never lower than `confirmed`.
+    pub fn confirm_commitment(&self) -> CommitmentConfig {
+        self.confirm_commitment
+    }
 
-                match transaction_meta.err {
-                    None => Ok(details),
-                    Some(error) => Err(ClientError::FailedTransaction { error, details }),
+    /// Polls the account at the given address until `predicate` returns true.
+    ///
+    /// The account is read at the configured commitment level.
+    /// A missing account is treated as not satisfying the predicate yet.
+    /// Returns `ClientError::Timeout` if the condition is not met within `timeout`.
+    pub fn wait_for_account(
+        &self,
+        address: Pubkey,
+        mut predicate: impl FnMut(&Account) -> bool,
+        timeout: Duration,
+    ) -> Result<Account, ClientError<SolanaClientError>> {
+        let start = Instant::now();
+        loop {
+            match get_existing_account(&self.client, &address, self.commitment) {
+                Ok(account) if predicate(&account) => return Ok(account),
+                Ok(_) | Err(ClientError::AccountNotFound(_)) => (),
+                Err(err) => return Err(err),
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(ClientError::Timeout);
+            }
+            // FIXME: Justify the sleep amount
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    /// Gets account information at the given address, as seen by the RPC node
+    /// at `slot` or a later slot.
+    ///
+    /// This is best-effort. RPC nodes do not serve historical account states,
+    /// so this only guarantees that the node has processed `slot` before answering,
+    /// and the request fails if the node has not reached `slot` yet.
+    pub fn get_account_at_slot(
+        &self,
+        address: Pubkey,
+        slot: Slot,
+    ) -> Result<Account, ClientError<SolanaClientError>> {
+        self.client
+            .get_account_with_config(
+                &address,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(self.commitment),
+                    min_context_slot: Some(slot),
+                    ..Default::default()
+                },
+            )
+            .map_err(convert_error)?
+            .value
+            .ok_or(ClientError::AccountNotFound(address))
+    }
+
+    /// Waits until every transaction reaches the confirmation commitment.
+    ///
+    /// `pending` is a list of pairs of the transaction signature and its recent blockhash.
+    /// If the blockhash is unknown, the expiry of the transaction cannot be detected,
+    /// so only the timeout stops waiting for a transaction that never lands.
+    fn wait_for_confirmation(
+        &self,
+        mut pending: Vec<(Signature, Option<Hash>)>,
+    ) -> Result<(), ClientError<SolanaClientError>> {
+        let start = Instant::now();
+        while !pending.is_empty() {
+            let mut still_pending = Vec::new();
+            for chunk in pending.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+                let chunk_signatures: Vec<_> =
+                    chunk.iter().map(|(signature, _)| *signature).collect();
+                let statuses = self
+                    .client
+                    .get_signature_statuses(&chunk_signatures)
+                    .map_err(convert_error)?
+                    .value;
+                for (&(signature, blockhash), status) in chunk.iter().zip(statuses) {
+                    match status {
+                        Some(status) if status.satisfies_commitment(self.confirm_commitment) => (),
+                        // The transaction has landed, so its blockhash no longer matters
+                        Some(_) => still_pending.push((signature, blockhash, true)),
+                        None => still_pending.push((signature, blockhash, false)),
+                    }
                 }
             }
-            Err(mut err) => {
-                if let SolanaClientErrorKind::RpcError(RpcError::RpcResponseError {
-                    code,
-                    data,
-                    ..
-                }) = &mut err.kind
-                {
-                    if *code == JSON_RPC_SERVER_ERROR_TRANSACTION_SIGNATURE_VERIFICATION_FAILURE {
+
+            // Each distinct blockhash is checked once per poll,
+            // since batched writes share a blockhash across many transactions
+            let mut blockhash_validity = HashMap::new();
+            for (_, blockhash, landed) in &still_pending {
+                if let (false, Some(blockhash)) = (landed, blockhash) {
+                    let valid = match blockhash_validity.get(blockhash) {
+                        Some(&valid) => valid,
+                        None => {
+                            let valid = self
+                                .client
+                                .is_blockhash_valid(blockhash, CommitmentConfig::processed())
+                                .map_err(convert_error)?;
+                            blockhash_validity.insert(*blockhash, valid);
+                            valid
+                        }
+                    };
+                    if !valid {
                         return Err(ClientError::InvalidTransaction(
-                            TransactionError::SignatureFailure,
+                            TransactionError::BlockhashNotFound,
                         ));
-                    } else if *code == JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE {
-                        if let RpcResponseErrorData::SendTransactionPreflightFailure(
-                            simulation_result,
-                        ) = data
-                        {
-                            return Err(ClientError::FailedTransaction {
-                                error: simulation_result.err.take().unwrap(),
-                                details: TransactionDetails {
-                                    log_messages: simulation_result.logs.take().unwrap_or_default(),
-                                    units_consumed: simulation_result.units_consumed.take(),
-                                },
-                            });
-                        }
                     }
                 }
+            }
 
-                Err(err.into())
+            pending = still_pending
+                .into_iter()
+                .map(|(signature, blockhash, _)| (signature, blockhash))
+                .collect();
+            if !pending.is_empty() {
+                if matches!(self.timeout, Some(timeout) if start.elapsed() >= timeout) {
+                    return Err(ClientError::Timeout);
+                }
+                // FIXME: Justify the sleep amount
+                std::thread::sleep(Duration::from_millis(100));
             }
         }
+
+        Ok(())
+    }
+}
+
+impl Environment<RemoteClientSync> {
+    /// Gets account information at the given address as of `slot` or a later slot.
+    ///
+    /// See [RemoteClientSync::get_account_at_slot] for the limitations.
+    pub fn get_account_at_slot(
+        &self,
+        address: Pubkey,
+        slot: Slot,
+    ) -> Result<Account, ClientError<SolanaClientError>> {
+        self.client.get_account_at_slot(address, slot)
+    }
+
+    /// Polls the account at the given address until `predicate` returns true.
+    ///
+    /// See [RemoteClientSync::wait_for_account] for details.
+    pub fn wait_for_account(
+        &self,
+        address: Pubkey,
+        predicate: impl FnMut(&Account) -> bool,
+        timeout: Duration,
+    ) -> Result<Account, ClientError<SolanaClientError>> {
+        self.client.wait_for_account(address, predicate, timeout)
+    }
+}
+
+impl ClientSync for RemoteClientSync {
+    type ChannelError = SolanaClientError;
+
+    fn send_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        let signature = send_with_preflight_commitment(&self.client, &transaction, self.commitment)
+            .map_err(convert_send_error)?;
+        self.wait_for_confirmation(vec![(
+            signature,
+            Some(transaction.message.recent_blockhash),
+        )])?;
+        get_transaction_details(&self.client, &signature, self.confirm_commitment)
+    }
+
+    fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionDetails>, ClientError<Self::ChannelError>> {
+        // Submit every transaction first and confirm them together,
+        // so that the confirmation latency is paid only once
+        let signatures = transactions
+            .iter()
+            .map(|transaction| {
+                send_with_preflight_commitment(&self.client, transaction, self.commitment)
+                    .map_err(convert_send_error)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let pending: Vec<_> = signatures
+            .iter()
+            .zip(transactions.iter())
+            .map(|(signature, transaction)| {
+                (*signature, Some(transaction.message.recent_blockhash))
+            })
+            .collect();
+        self.wait_for_confirmation(pending)?;
+
+        signatures
+            .iter()
+            .map(|signature| {
+                get_transaction_details(&self.client, signature, self.confirm_commitment)
+            })
+            .collect()
+    }
+
+    fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        self.wait_for_confirmation(vec![(signature, None)])?;
+        get_transaction_details(&self.client, &signature, self.confirm_commitment)
     }
 
     fn latest_blockhash(&mut self) -> Result<Hash, Self::ChannelError> {
-        Ok(self.client.get_latest_blockhash()?)
+        let (blockhash, _) = self
+            .client
+            .get_latest_blockhash_with_commitment(self.commitment)?;
+        Ok(blockhash)
     }
 
     fn tick_beyond(&mut self, blockhash: Hash) -> Result<Hash, Self::ChannelError> {
-        let mut last_hash = self.client.get_latest_blockhash()?;
+        let mut last_hash = self.latest_blockhash()?;
         while last_hash == blockhash {
             // FIXME: Justify the sleep amount
             std::thread::sleep(Duration::from_millis(100));
-            last_hash = self.client.get_latest_blockhash()?;
+            last_hash = self.latest_blockhash()?;
         }
 
         Ok(last_hash)
     }
 
+    fn get_fee_for_message(
+        &mut self,
+        message: &Message,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        self.client
+            .get_fee_for_message(message)
+            .map_err(convert_error)
+    }
+
+    fn minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        self.client
+            .get_minimum_balance_for_rent_exemption(data_len)
+            .map_err(convert_error)
+    }
+
     fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientError<Self::ChannelError>> {
-        get_existing_account(&mut self.client, &address)
+        get_existing_account(&self.client, &address, self.commitment)
+    }
+
+    fn get_account_data_slice(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, ClientError<Self::ChannelError>> {
+        Ok(self
+            .client
+            .get_account_with_config(
+                &address,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    data_slice: Some(UiDataSliceConfig { offset, length }),
+                    commitment: Some(self.commitment),
+                    ..Default::default()
+                },
+            )
+            .map_err(convert_error)?
+            .value
+            .ok_or(ClientError::AccountNotFound(address))?
+            .data)
+    }
+
+    fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError<Self::ChannelError>> {
+        let filters = filters
+            .iter()
+            .map(|filter| match filter {
+                AccountFilter::DataSize(size) => RpcFilterType::DataSize(*size),
+                AccountFilter::Memcmp { offset, bytes } => RpcFilterType::Memcmp(Memcmp {
+                    offset: *offset,
+                    bytes: MemcmpEncodedBytes::Base64(base64::encode(bytes)),
+                    encoding: None,
+                }),
+            })
+            .collect();
+
+        Ok(self
+            .client
+            .get_program_accounts_with_config(
+                &program_id,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        commitment: Some(self.commitment),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .map_err(convert_error)?)
     }
 }
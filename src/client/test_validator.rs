@@ -0,0 +1,169 @@
+use solana_client::client_error::ClientError as SolanaClientError;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
+    commitment_config::CommitmentConfig,
+    feature_set,
+    hash::Hash,
+    message::Message,
+    native_token::sol_to_lamports,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    system_program,
+    transaction::Transaction,
+};
+use solana_test_validator::{TestValidator, TestValidatorGenesis};
+
+use super::{remote::RemoteClientSync, AccountFilter, ClientError, ClientSync, TransactionDetails};
+use crate::{Environment, EnvironmentGenesis};
+
+/// A client backed by an in-process `solana-test-validator`.
+///
+/// Unlike [LocalClientSync](super::LocalClientSync), the validator exposes a real RPC endpoint,
+/// so external tools such as explorers and wallets can inspect the PoC state.
+pub struct TestValidatorClientSync {
+    validator: TestValidator,
+    client: RemoteClientSync,
+}
+
+impl TestValidatorClientSync {
+    pub(crate) fn new(
+        mut genesis: EnvironmentGenesis,
+    ) -> Result<Environment<Self>, ClientError<SolanaClientError>> {
+        let rent = Rent::default();
+
+        // Builtin programs cannot be registered to the validator
+        let default_loaders = [
+            bpf_loader_deprecated::ID,
+            bpf_loader::ID,
+            bpf_loader_upgradeable::ID,
+        ];
+        if genesis
+            .loaders
+            .iter()
+            .any(|(_, program_id, _)| !default_loaders.contains(program_id))
+        {
+            return Err(ClientError::UnsupportedOption(
+                "a builtin loader or processor",
+            ));
+        }
+
+        // The validator activates every feature by default,
+        // while the local bank only activates the features listed in the genesis
+        let deactivated_features: Vec<_> = feature_set::FEATURE_NAMES
+            .keys()
+            .filter(|feature_id| !genesis.activated_features.contains(*feature_id))
+            .copied()
+            .collect();
+
+        // The validator is always local, so waiting for finalization only slows down the PoC
+        genesis
+            .commitment
+            .get_or_insert_with(CommitmentConfig::confirmed);
+
+        let payer = match genesis.payer.take() {
+            Some(keypair) => keypair,
+            None => Keypair::new(),
+        };
+
+        let mut validator_genesis = TestValidatorGenesis::default();
+        validator_genesis
+            .rent(rent)
+            .fee_rate_governor(genesis.fee_rate_governor())
+            .deactivate_features(&deactivated_features)
+            .add_accounts(genesis.accounts.iter().map(|(&address, account_config)| {
+                (address, account_config.clone().to_account(&rent))
+            }))
+            .add_account(
+                payer.pubkey(),
+                AccountSharedData::new(sol_to_lamports(1_000_000_000.0), 0, &system_program::ID),
+            );
+
+        let (validator, _mint_keypair) = validator_genesis.start();
+        let rpc_url = validator.rpc_url();
+
+        RemoteClientSync::new_wrapped(genesis.add_payer(payer), rpc_url, |client| {
+            TestValidatorClientSync { validator, client }
+        })
+    }
+
+    /// Returns the underlying test validator.
+    pub fn validator(&self) -> &TestValidator {
+        &self.validator
+    }
+
+    /// Returns the RPC URL of the underlying test validator.
+    pub fn rpc_url(&self) -> String {
+        self.validator.rpc_url()
+    }
+}
+
+impl ClientSync for TestValidatorClientSync {
+    type ChannelError = SolanaClientError;
+
+    fn send_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        self.client.send_transaction(transaction)
+    }
+
+    fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionDetails>, ClientError<Self::ChannelError>> {
+        self.client.send_transactions(transactions)
+    }
+
+    fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientError<Self::ChannelError>> {
+        self.client.confirm_transaction(signature)
+    }
+
+    fn latest_blockhash(&mut self) -> Result<Hash, Self::ChannelError> {
+        self.client.latest_blockhash()
+    }
+
+    fn tick_beyond(&mut self, blockhash: Hash) -> Result<Hash, Self::ChannelError> {
+        self.client.tick_beyond(blockhash)
+    }
+
+    fn get_fee_for_message(
+        &mut self,
+        message: &Message,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        self.client.get_fee_for_message(message)
+    }
+
+    fn minimum_balance_for_rent_exemption(
+        &mut self,
+        data_len: usize,
+    ) -> Result<u64, ClientError<Self::ChannelError>> {
+        self.client.minimum_balance_for_rent_exemption(data_len)
+    }
+
+    fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientError<Self::ChannelError>> {
+        self.client.get_account(address)
+    }
+
+    fn get_account_data_slice(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, ClientError<Self::ChannelError>> {
+        self.client.get_account_data_slice(address, offset, length)
+    }
+
+    fn get_program_accounts(
+        &mut self,
+        program_id: Pubkey,
+        filters: &[AccountFilter],
+    ) -> Result<Vec<(Pubkey, Account)>, ClientError<Self::ChannelError>> {
+        self.client.get_program_accounts(program_id, filters)
+    }
+}
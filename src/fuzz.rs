@@ -0,0 +1,130 @@
+//! Randomized instruction sequence testing.
+//!
+//! [FuzzEnvironment] throws randomized sequences of instructions at a program
+//! and reports transactions that abort the program (e.g., a panic or an access violation),
+//! as opposed to transactions that cleanly return an error.
+
+use arbitrary::Unstructured;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use solana_sdk::instruction::Instruction;
+
+use crate::{
+    client::{ClientError, ClientSync, TransactionDetails},
+    ClientErrorSync, Environment,
+};
+
+/// Number of random bytes fed to the generators for each sequence.
+const INPUT_SIZE: usize = 4096;
+
+/// A function that builds an instruction from unstructured random input.
+pub type InstructionGenerator<'a> =
+    Box<dyn FnMut(&mut Unstructured) -> arbitrary::Result<Instruction> + 'a>;
+
+/// A transaction that aborted the program during fuzzing.
+#[derive(Debug)]
+pub struct FuzzFinding {
+    /// Seed of the sequence, which can be replayed with [FuzzEnvironment::run_sequence].
+    pub seed: u64,
+    /// Instructions executed in the sequence, where the last one aborted the program.
+    pub instructions: Vec<Instruction>,
+    pub details: TransactionDetails,
+}
+
+/// Applies randomized instruction sequences to fresh environments.
+///
+/// Every sequence starts from an environment created by `setup`,
+/// so each finding can be reproduced from its seed alone.
+pub struct FuzzEnvironment<'a, C> {
+    setup: Box<dyn FnMut() -> Environment<C> + 'a>,
+    generators: Vec<InstructionGenerator<'a>>,
+    rng: StdRng,
+    max_sequence_len: usize,
+}
+
+impl<'a, C: ClientSync> FuzzEnvironment<'a, C> {
+    /// Creates a fuzzing harness whose sequences are derived from `seed`.
+    pub fn new(seed: u64, setup: impl FnMut() -> Environment<C> + 'a) -> Self {
+        FuzzEnvironment {
+            setup: Box::new(setup),
+            generators: Vec::new(),
+            rng: StdRng::seed_from_u64(seed),
+            max_sequence_len: 8,
+        }
+    }
+
+    /// Adds an instruction generator to the corpus.
+    pub fn add_generator(
+        &mut self,
+        generator: impl FnMut(&mut Unstructured) -> arbitrary::Result<Instruction> + 'a,
+    ) -> &mut Self {
+        self.generators.push(Box::new(generator));
+        self
+    }
+
+    /// Sets the maximum number of instructions in a sequence. The default is 8.
+    pub fn max_sequence_len(&mut self, max_sequence_len: usize) -> &mut Self {
+        self.max_sequence_len = max_sequence_len;
+        self
+    }
+
+    /// Runs `iterations` randomized sequences and returns the ones that aborted the program.
+    ///
+    /// Instructions that cleanly fail or are rejected by the runtime are ignored
+    /// and the sequence continues.
+    pub fn run(&mut self, iterations: usize) -> Result<Vec<FuzzFinding>, ClientErrorSync<C>> {
+        let mut findings = Vec::new();
+        for _ in 0..iterations {
+            let seed = self.rng.gen();
+            if let Some(finding) = self.run_sequence(seed)? {
+                findings.push(finding);
+            }
+        }
+        Ok(findings)
+    }
+
+    /// Runs a single sequence derived from `seed` and returns the finding if the program aborted.
+    pub fn run_sequence(&mut self, seed: u64) -> Result<Option<FuzzFinding>, ClientErrorSync<C>> {
+        assert!(
+            !self.generators.is_empty(),
+            "At least one instruction generator should be added"
+        );
+
+        let mut input = vec![0u8; INPUT_SIZE];
+        StdRng::seed_from_u64(seed).fill(&mut input[..]);
+        let mut unstructured = Unstructured::new(&input);
+
+        let mut env = (self.setup)();
+        let mut instructions = Vec::new();
+
+        let sequence_len = unstructured
+            .int_in_range(1..=self.max_sequence_len.max(1))
+            .unwrap_or(1);
+        for _ in 0..sequence_len {
+            let instruction = match unstructured
+                .int_in_range(0..=self.generators.len() - 1)
+                .and_then(|index| (self.generators[index])(&mut unstructured))
+            {
+                Ok(instruction) => instruction,
+                // The random input is exhausted
+                Err(_) => break,
+            };
+            instructions.push(instruction.clone());
+
+            match env.run_instructions(&[instruction], &[]) {
+                Err(ClientError::FailedTransaction { details, .. }) if details.aborted() => {
+                    return Ok(Some(FuzzFinding {
+                        seed,
+                        instructions,
+                        details,
+                    }));
+                }
+                Ok(())
+                | Err(ClientError::FailedTransaction { .. })
+                | Err(ClientError::InvalidTransaction(_)) => (),
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,31 @@
+//! Helpers for building instructions of non-SPL programs
+//! whose instruction data is serialized with borsh or bincode.
+
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Builds an instruction whose data is `data` serialized with borsh.
+#[cfg(feature = "borsh")]
+pub fn instruction_with_borsh<T: borsh::BorshSerialize>(
+    program_id: Pubkey,
+    accounts: Vec<AccountMeta>,
+    data: &T,
+) -> Instruction {
+    let data = data
+        .try_to_vec()
+        .expect("Instruction data should be serializable with borsh");
+    Instruction::new_with_bytes(program_id, &data, accounts)
+}
+
+/// Builds an instruction whose data is `data` serialized with bincode.
+pub fn instruction_with_bincode<T: serde::Serialize>(
+    program_id: Pubkey,
+    accounts: Vec<AccountMeta>,
+    data: &T,
+) -> Instruction {
+    let data =
+        bincode::serialize(data).expect("Instruction data should be serializable with bincode");
+    Instruction::new_with_bytes(program_id, &data, accounts)
+}
@@ -0,0 +1,84 @@
+//! Account JSON format used by `solana account --output json`.
+
+use std::{fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use crate::AccountConfig;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct JsonAccountEntry {
+    pub pubkey: String,
+    pub account: JsonAccount,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonAccount {
+    pub lamports: u64,
+    /// A pair of the encoded data and the encoding, e.g., `["AAEC", "base64"]`
+    pub data: (String, String),
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+}
+
+impl JsonAccountEntry {
+    pub fn from_account(address: Pubkey, account: &Account) -> Self {
+        JsonAccountEntry {
+            pubkey: address.to_string(),
+            account: JsonAccount {
+                lamports: account.lamports,
+                data: (base64::encode(&account.data), "base64".to_string()),
+                owner: account.owner.to_string(),
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+            },
+        }
+    }
+
+    pub fn into_account_config(self) -> (Pubkey, AccountConfig) {
+        let address = self.pubkey.parse().expect("Invalid account address");
+
+        let (data, encoding) = self.account.data;
+        assert_eq!(encoding, "base64", "Unsupported account data encoding");
+        let data = base64::decode(data).expect("Invalid base64 account data");
+
+        (
+            address,
+            AccountConfig {
+                lamports: Some(self.account.lamports),
+                data,
+                owner: self.account.owner.parse().expect("Invalid account owner"),
+                executable: self.account.executable,
+                rent_epoch: Some(self.account.rent_epoch),
+            },
+        )
+    }
+}
+
+/// Reads account dumps from a JSON file or from every JSON file in a directory.
+pub(crate) fn read_accounts(path: &Path) -> Vec<(Pubkey, AccountConfig)> {
+    if path.is_dir() {
+        let mut paths: Vec<_> = fs::read_dir(path)
+            .expect("Failed to read account dump directory")
+            .map(|entry| entry.expect("Failed to read account dump directory").path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .collect();
+        // Sort paths for a deterministic order
+        paths.sort();
+
+        paths.iter().map(|path| read_account(path)).collect()
+    } else {
+        vec![read_account(path)]
+    }
+}
+
+fn read_account(path: &Path) -> (Pubkey, AccountConfig) {
+    let file = fs::File::open(path)
+        .unwrap_or_else(|err| panic!("Failed to open {}: {}", path.display(), err));
+    let entry: JsonAccountEntry = serde_json::from_reader(file)
+        .unwrap_or_else(|err| panic!("Failed to parse {}: {}", path.display(), err));
+    entry.into_account_config()
+}
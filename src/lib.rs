@@ -1,27 +1,64 @@
-use std::{collections::HashMap, sync::atomic::Ordering};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Duration,
+};
 
-use client::{local, remote::RemoteClientSync, ClientError, ClientSync, LocalClientSync};
+use client::{
+    local, remote::RemoteClientSync, AccountFilter, ClientError, ClientSync, DryRunClientSync,
+    LocalClientSync, TransactionDetails,
+};
 use log::{info, warn};
+use solana_client::rpc_client::RpcClient;
+use solana_program_runtime::invoke_context::ProcessInstructionWithContext;
 use solana_program_test::programs::spl_programs;
 use solana_sdk::{
     account::{Account, AccountSharedData, ReadableAccount},
+    account_utils::StateMut,
     bpf_loader,
     bpf_loader_upgradeable::{self, UpgradeableLoaderState},
-    hash::Hash,
-    instruction::Instruction,
+    clock::{Clock, Slot, UnixTimestamp},
+    commitment_config::CommitmentConfig,
+    compute_budget,
+    epoch_schedule::EpochSchedule,
+    feature_set,
+    fee_calculator::{FeeRateGovernor, DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE},
+    hash::{hash, Hash},
+    instruction::{AccountMeta, Instruction},
     loader_instruction,
-    message::Message,
+    message::{Message, SanitizedMessage},
+    native_loader,
+    packet::PACKET_DATA_SIZE,
     program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
-    signature::Keypair,
+    sdk_ids,
+    signature::{keypair_from_seed, Keypair, Signature},
     signer::Signer,
-    system_instruction, system_program,
-    transaction::Transaction,
+    slot_hashes::SlotHashes,
+    stake::{
+        self,
+        state::{Authorized, Lockup, Meta, StakeState},
+    },
+    stake_history::StakeHistory,
+    system_instruction::{self, MAX_PERMITTED_DATA_LENGTH},
+    system_program, sysvar,
+    transaction::{Transaction, TransactionError},
 };
+use solana_vote_program::vote_state::{self, VoteState};
 use spl_associated_token_account::get_associated_token_address;
+use spl_token::instruction::AuthorityType;
+use transaction::PartialTransaction;
 
 pub mod client;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod ix;
+mod json;
+mod script;
+pub mod transaction;
 
 #[derive(Clone)]
 pub struct AccountConfig {
@@ -31,6 +68,9 @@ pub struct AccountConfig {
     pub data: Vec<u8>,
     pub owner: Pubkey,
     pub executable: bool,
+    /// Epoch at which this account will next owe rent.
+    /// If omitted, it will be set to 0.
+    pub rent_epoch: Option<u64>,
 }
 
 impl Default for AccountConfig {
@@ -40,31 +80,50 @@ impl Default for AccountConfig {
             data: Vec::new(),
             owner: system_program::id(),
             executable: false,
+            rent_epoch: None,
         }
     }
 }
 
 impl AccountConfig {
+    /// Creates an empty account owned by `owner` that is funded with
+    /// the exact rent-exempt minimum for its data length when the environment is built.
+    ///
+    /// The data can be set afterwards, e.g., `AccountConfig { data, ..AccountConfig::rent_exempt(owner) }`.
+    pub fn rent_exempt(owner: Pubkey) -> Self {
+        AccountConfig {
+            lamports: None,
+            owner,
+            ..Default::default()
+        }
+    }
+
     pub fn to_account(self, rent: &Rent) -> AccountSharedData {
         Account {
             lamports: self
                 .lamports
-                .unwrap_or_else(|| rent.minimum_balance(self.data.len()).min(1)),
+                // Zero-lamport accounts are not stored, so keep at least one lamport
+                .unwrap_or_else(|| rent.minimum_balance(self.data.len()).max(1)),
             data: self.data,
             owner: self.owner,
             executable: self.executable,
-            rent_epoch: 0,
+            rent_epoch: self.rent_epoch.unwrap_or(0),
         }
         .into()
     }
 }
 
-#[derive(Default)]
+/// A builtin program loader, consisting of the name, the program ID, and the entrypoint.
+pub type Loader = (String, Pubkey, ProcessInstructionWithContext);
+
+#[derive(Clone, Copy, Default)]
 pub struct LogConfig {
     /// Whether to log successful transaction result
     pub log_successful_transaction: bool,
     /// Whether to log failed transaction result
     pub log_failed_transaction: bool,
+    /// Whether to log a [RunSummary] when the environment is dropped
+    pub log_summary_on_drop: bool,
 }
 
 impl LogConfig {
@@ -80,6 +139,7 @@ impl LogConfig {
         LogConfig {
             log_successful_transaction: false,
             log_failed_transaction: true,
+            log_summary_on_drop: false,
         }
     }
 
@@ -87,6 +147,47 @@ impl LogConfig {
         LogConfig {
             log_successful_transaction: true,
             log_failed_transaction: true,
+            log_summary_on_drop: false,
+        }
+    }
+}
+
+/// Statistics of the transactions sent by an environment.
+#[derive(Clone, Debug, Default)]
+pub struct RunSummary {
+    /// Number of sent transactions, including the failed ones
+    pub transactions_sent: u64,
+    /// See [Environment::total_units_consumed]
+    pub total_units_consumed: u64,
+    /// Net lamport changes of the labeled accounts since the first transaction.
+    /// Accounts without changes are omitted.
+    pub lamport_changes: Vec<(String, i128)>,
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Transactions sent: {}", self.transactions_sent)?;
+        write!(f, "Compute units consumed: {}", self.total_units_consumed)?;
+        for (label, change) in &self.lamport_changes {
+            write!(f, "\n{}: {:+} lamports", label, change)?;
+        }
+        Ok(())
+    }
+}
+
+/// States needed to log a [RunSummary] of an environment when it is dropped.
+struct SummaryReporter<C> {
+    /// Balances of the labeled accounts before the first transaction
+    initial_balances: Option<HashMap<Pubkey, u64>>,
+    /// [Environment::run_summary], captured where the client is known to implement [ClientSync]
+    run_summary: fn(&mut Environment<C>) -> RunSummary,
+}
+
+impl<C: ClientSync> Default for SummaryReporter<C> {
+    fn default() -> Self {
+        SummaryReporter {
+            initial_balances: None,
+            run_summary: Environment::run_summary,
         }
     }
 }
@@ -96,6 +197,20 @@ pub struct EnvironmentGenesis {
     address_labels: HashMap<Pubkey, String>,
     payer: Option<Keypair>,
     log_config: Option<LogConfig>,
+    commitment: Option<CommitmentConfig>,
+    confirm_commitment: Option<CommitmentConfig>,
+    fees_enabled: bool,
+    activated_features: HashSet<Pubkey>,
+    auto_advance_blockhash: bool,
+    batched_writes: bool,
+    write_chunk_size: usize,
+    timeout: Option<Duration>,
+    loaders: Vec<Loader>,
+    payer_balance_warning: Option<u64>,
+    forbidden_clusters: HashSet<Hash>,
+    recent_logs_capacity: usize,
+    initial_slot: Slot,
+    transaction_script: Option<PathBuf>,
 }
 
 impl EnvironmentGenesis {
@@ -104,12 +219,35 @@ impl EnvironmentGenesis {
         Self::default()
     }
 
+    /// Derives a keypair deterministically from an arbitrary seed.
+    ///
+    /// The seed is hashed into an ed25519 seed, so the same seed always yields the same keypair.
+    /// This is useful for making PoC outputs stable across runs.
+    pub fn keypair_from_seed(seed: &[u8]) -> Keypair {
+        keypair_from_seed(&hash(seed).to_bytes()).expect("Hash should be a valid ed25519 seed")
+    }
+
+    /// Adds an account at the address of [Self::keypair_from_seed] and labels it.
+    ///
+    /// The keypair can be recovered later by calling [Self::keypair_from_seed] with the same seed.
+    pub fn add_account_from_seed(
+        self,
+        label: impl ToString,
+        seed: &[u8],
+        config: AccountConfig,
+    ) -> Self {
+        let address = Self::keypair_from_seed(seed).pubkey();
+        self.add_account(address, config)
+            .add_address_label(label, address)
+    }
+
     /// Adds a payer keypair who will pay for the transaction.
     ///
     /// In local environment, a new account that holds huge amount of lamports will be added to the address.
     /// If omitted, a new keypair will be automatically generated when building the local environment.
     ///
-    /// In remote environment, payer must be provided when the client is created.
+    /// In remote environment, payer must be provided when the client is created,
+    /// or building the environment fails with `ClientError::PayerNotSpecified`.
     pub fn add_payer(mut self, keypair: Keypair) -> Self {
         let pubkey = keypair.pubkey();
         assert!(self.payer.is_none());
@@ -131,6 +269,88 @@ impl EnvironmentGenesis {
         self
     }
 
+    /// Adds accounts dumped with `solana account --output json` to the initial account set.
+    ///
+    /// `path` can be either a single JSON file or a directory of JSON files.
+    pub fn add_accounts_from_json(mut self, path: impl AsRef<Path>) -> Self {
+        for (address, config) in json::read_accounts(path.as_ref()) {
+            self = self.add_account(address, config);
+        }
+        self
+    }
+
+    /// Adds an initialized vote account to the initial account set.
+    ///
+    /// The account holds the rent-exempt amount of lamports.
+    pub fn add_vote_account(
+        self,
+        address: Pubkey,
+        node: Pubkey,
+        authorized_voter: Pubkey,
+        authorized_withdrawer: Pubkey,
+        commission: u8,
+    ) -> Self {
+        let lamports = Rent::default().minimum_balance(VoteState::size_of());
+        let account = vote_state::create_account_with_authorized(
+            &node,
+            &authorized_voter,
+            &authorized_withdrawer,
+            commission,
+            lamports,
+        );
+        self.add_account(
+            address,
+            AccountConfig {
+                lamports: Some(lamports),
+                data: account.data().to_vec(),
+                owner: solana_vote_program::id(),
+                executable: false,
+                rent_epoch: None,
+            },
+        )
+    }
+
+    /// Adds an initialized, undelegated stake account to the initial account set.
+    ///
+    /// `lamports` should be at least the rent-exempt amount of a stake account,
+    /// and the excess is the amount that can be delegated.
+    pub fn add_stake_account(
+        self,
+        address: Pubkey,
+        staker: Pubkey,
+        withdrawer: Pubkey,
+        lamports: u64,
+    ) -> Self {
+        let rent_exempt_reserve = Rent::default().minimum_balance(StakeState::size_of());
+        assert!(
+            lamports >= rent_exempt_reserve,
+            "Stake account should hold at least the rent-exempt amount"
+        );
+
+        let state = StakeState::Initialized(Meta {
+            rent_exempt_reserve,
+            authorized: Authorized { staker, withdrawer },
+            lockup: Lockup::default(),
+        });
+        let account = AccountSharedData::new_data_with_space(
+            lamports,
+            &state,
+            StakeState::size_of(),
+            &stake::program::ID,
+        )
+        .expect("Stake state should be serializable");
+        self.add_account(
+            address,
+            AccountConfig {
+                lamports: Some(lamports),
+                data: account.data().to_vec(),
+                owner: stake::program::ID,
+                executable: false,
+                rent_epoch: None,
+            },
+        )
+    }
+
     /// Adds a program to the initial account set.
     pub fn add_program(mut self, address: Pubkey, data: Vec<u8>) -> Self {
         assert!(
@@ -169,12 +389,244 @@ impl EnvironmentGenesis {
         self
     }
 
+    /// Sets the commitment level used by the remote client for account reads,
+    /// blockhashes, and the preflight simulation of sent transactions.
+    ///
+    /// Transaction confirmation uses the same commitment
+    /// unless it is overridden with [Self::with_confirm_commitment].
+    /// `confirmed` is faster when running against a local validator,
+    /// while `finalized` is safer when forking mainnet state.
+    /// The default is `finalized` for remote environment
+    /// and `confirmed` for the test validator, which is always local.
+    /// Transaction confirmation never goes below `confirmed`, even if `processed` is set.
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = Some(commitment);
+        self
+    }
+
+    /// Sets the commitment level the remote client waits for after sending a transaction.
+    ///
+    /// Waiting for `confirmed` returns about a dozen seconds earlier than `finalized`,
+    /// but a confirmed transaction can still be rolled back in a rare fork.
+    /// `processed` is raised to `confirmed`, because the RPC does not serve
+    /// the details of a transaction before it is confirmed.
+    /// Since account reads keep using the commitment set with [Self::with_commitment],
+    /// a read right after a transaction confirmed at a lower level may not observe its effect
+    /// until the transaction reaches the read commitment.
+    pub fn with_confirm_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.confirm_commitment = Some(commitment);
+        self
+    }
+
+    /// Refuses to build a remote environment connected to the cluster with `genesis_hash`.
+    ///
+    /// Building the environment fails with `ClientError::ForbiddenCluster`
+    /// before any transaction is sent.
+    pub fn forbid_cluster(mut self, genesis_hash: Hash) -> Self {
+        self.forbidden_clusters.insert(genesis_hash);
+        self
+    }
+
+    /// Refuses to build a remote environment connected to mainnet-beta.
+    ///
+    /// This is a safety rail against accidentally running a PoC on mainnet.
+    pub fn forbid_mainnet(self) -> Self {
+        self.forbid_cluster(
+            MAINNET_BETA_GENESIS_HASH
+                .parse()
+                .expect("Mainnet genesis hash should be valid"),
+        )
+    }
+
+    /// Sets the maximum duration that the remote client waits for a transaction confirmation.
+    ///
+    /// `ClientError::Timeout` is returned if a transaction is not confirmed within the duration.
+    /// Without a timeout, the client waits until the blockhash of the transaction expires.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether the local bank charges transaction fees.
+    ///
+    /// Fees are disabled by default. When enabled, each signature costs
+    /// `DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE` lamports and the rate does not fluctuate.
+    /// This has no effect on remote environment.
+    pub fn with_fees(mut self, enabled: bool) -> Self {
+        self.fees_enabled = enabled;
+        self
+    }
+
+    /// Returns the fee rate governor configured with [Self::with_fees].
+    fn fee_rate_governor(&self) -> FeeRateGovernor {
+        if self.fees_enabled {
+            // Setting the target signatures per slot to zero keeps the fee rate fixed
+            FeeRateGovernor::new(DEFAULT_TARGET_LAMPORTS_PER_SIGNATURE, 0)
+        } else {
+            // The default governor derives a nonzero fee rate for every child bank,
+            // so the zero rate should be the target as well
+            FeeRateGovernor::new(0, 0)
+        }
+    }
+
+    /// Sets whether to advance the blockhash before sending a transaction
+    /// when it did not change since the last transaction.
+    ///
+    /// The blockhash of the local bank does not change unless it ticks,
+    /// so sending identical instructions twice is rejected as a duplicate transaction.
+    /// Enabling this option makes such sends succeed at the cost of extra ticks.
+    pub fn with_auto_advance_blockhash(mut self, enabled: bool) -> Self {
+        self.auto_advance_blockhash = enabled;
+        self
+    }
+
+    /// Sets whether to send the loader write transactions of program deployment in a batch.
+    ///
+    /// Batched writes are enabled by default. On remote environment, all write transactions
+    /// are submitted before waiting for their confirmation, which makes deploying a large program
+    /// roughly as slow as a single confirmation instead of one confirmation per chunk.
+    /// Disabling this falls back to sending and confirming each chunk one by one.
+    pub fn with_batched_writes(mut self, enabled: bool) -> Self {
+        self.batched_writes = enabled;
+        self
+    }
+
+    /// Sets the size of the data chunk written by each loader write instruction
+    /// of program deployment. The default is 900 bytes.
+    ///
+    /// Larger chunks reduce the number of write transactions.
+    /// Panics if the size is zero or the write transactions would not fit in a single packet.
+    pub fn with_write_chunk_size(mut self, size: usize) -> Self {
+        assert!(
+            size > 0 && size <= MAX_WRITE_CHUNK_SIZE,
+            "Write chunk size should be between 1 and {} bytes",
+            MAX_WRITE_CHUNK_SIZE
+        );
+        self.write_chunk_size = size;
+        self
+    }
+
+    /// Records every transaction sent by the environment to a script file at `path`,
+    /// which can be replayed with [Environment::replay_script].
+    ///
+    /// Each line of the script contains the base64-encoded transaction
+    /// and the labels of its signers. The file is overwritten when the environment is built.
+    pub fn with_transaction_script(mut self, path: impl AsRef<Path>) -> Self {
+        self.transaction_script = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Logs a warning whenever the payer balance drops below `lamports` after sending a transaction.
+    ///
+    /// This helps to notice a drained payer on remote environment
+    /// before it causes confusing failures in the middle of a PoC.
+    pub fn with_payer_balance_warning(mut self, lamports: u64) -> Self {
+        self.payer_balance_warning = Some(lamports);
+        self
+    }
+
+    /// Sets the number of recent transaction details retained by the local client.
+    /// The default is 16.
+    ///
+    /// The details are available from [Environment::recent_logs]
+    /// even when the sending code did not keep the result.
+    /// This has no effect on remote environment.
+    pub fn with_recent_logs_capacity(mut self, capacity: usize) -> Self {
+        self.recent_logs_capacity = capacity;
+        self
+    }
+
+    /// Starts the local bank at `slot` instead of slot 0.
+    ///
+    /// The sysvars such as `Clock` and `SlotHashes` are updated for the initial slot,
+    /// as the bank is created as a child of the genesis bank.
+    /// This has no effect on remote environment.
+    pub fn with_initial_slot(mut self, slot: Slot) -> Self {
+        self.initial_slot = slot;
+        self
+    }
+
+    /// Activates a runtime feature in the local bank from the genesis.
+    ///
+    /// The local bank starts with no feature activated,
+    /// so behavior gated behind a feature needs to be explicitly activated.
+    /// This has no effect on remote environment.
+    pub fn activate_feature(mut self, feature_id: Pubkey) -> Self {
+        self.activated_features.insert(feature_id);
+        self
+    }
+
+    /// Activates every runtime feature known to the current version of Solana.
+    pub fn activate_all_features(mut self) -> Self {
+        self.activated_features
+            .extend(feature_set::FEATURE_NAMES.keys().copied());
+        self
+    }
+
+    /// Deactivates a runtime feature that was activated before.
+    ///
+    /// Combined with [Self::activate_all_features], this allows testing
+    /// the behavior before a specific runtime change.
+    pub fn deactivate_feature(mut self, feature_id: Pubkey) -> Self {
+        self.activated_features.remove(&feature_id);
+        self
+    }
+
+    /// Registers a builtin program loader to the local bank.
+    ///
+    /// `loader` is a tuple of the name, the program ID, and the entrypoint,
+    /// which is the form returned by the `declare_builtin!` macros of loader crates.
+    /// The three BPF loaders are registered by default.
+    /// This has no effect on remote environment.
+    pub fn add_loader(mut self, loader: Loader) -> Self {
+        assert!(
+            self.loaders.iter().all(|(_, id, _)| *id != loader.1),
+            "Loader added to the same address more than once"
+        );
+        self.loaders.push(loader);
+        self
+    }
+
+    /// Registers a program written with `solana_program` as a builtin of the local bank,
+    /// so it can be invoked without compiling it to BPF.
+    ///
+    /// The entrypoint can be created from the processor function with
+    /// `solana_program_test::processor!(process_instruction)`.
+    /// This has no effect on remote environment.
+    pub fn add_processor(
+        self,
+        program_id: Pubkey,
+        process_instruction: ProcessInstructionWithContext,
+    ) -> Self {
+        assert!(
+            !self.accounts.contains_key(&program_id),
+            "Processor added to an address with an existing account"
+        );
+        self.add_loader((program_id.to_string(), program_id, process_instruction))
+    }
+
     /// Builds a [LocalClientSync] from the current configuration.
     pub fn build_local_sync(self) -> Environment<LocalClientSync> {
         LogConfig::update_logger();
         LocalClientSync::new(self)
     }
 
+    /// Builds a [LocalTemplate](local::LocalTemplate) from the current configuration,
+    /// which builds multiple local environments with the same genesis.
+    pub fn build_local_template(self) -> local::LocalTemplate {
+        LogConfig::update_logger();
+        local::LocalTemplate::new(self)
+    }
+
+    /// Builds a [DryRunClientSync](client::DryRunClientSync) from the current configuration.
+    ///
+    /// The environment records the transactions instead of executing them,
+    /// and every transaction is reported as successful.
+    pub fn build_dry_run_sync(self) -> Environment<DryRunClientSync> {
+        LogConfig::update_logger();
+        DryRunClientSync::new(self)
+    }
+
     /// Builds a [RemoteClientSync] from the current configuration.
     pub fn build_remote_sync(
         self,
@@ -187,9 +639,54 @@ impl EnvironmentGenesis {
         RemoteClientSync::new(self, url.to_string())
     }
 
+    /// Builds a [RemoteClientSync] on top of an `RpcClient` constructed by the caller.
+    ///
+    /// This allows connecting to RPC endpoints that need a tuned timeout or a custom sender,
+    /// e.g., an authenticated endpoint of an RPC provider.
+    /// Account reads, blockhashes, and the preflight simulation of sent transactions
+    /// use the commitment level set with [Self::with_commitment] rather than the one of `client`,
+    /// and transaction confirmation uses [Self::with_confirm_commitment].
+    /// The commitment level of `client` only applies to the remaining queries, such as fee estimation.
+    pub fn build_remote_sync_with_client(
+        self,
+        client: RpcClient,
+    ) -> Result<
+        Environment<RemoteClientSync>,
+        ClientError<<RemoteClientSync as ClientSync>::ChannelError>,
+    > {
+        LogConfig::update_logger();
+        RemoteClientSync::with_client(self, client, |client| client)
+    }
+
+    /// Builds a [TestValidatorClientSync](client::TestValidatorClientSync)
+    /// from the current configuration.
+    ///
+    /// This starts a `solana-test-validator` in the current process,
+    /// which will be stopped when the environment is dropped.
+    /// Fees and runtime features are configured the same way as the local bank,
+    /// but builtin loaders and processors cannot be registered to the validator,
+    /// so adding them returns `ClientError::UnsupportedOption`.
+    #[cfg(feature = "test-validator")]
+    pub fn build_test_validator_sync(
+        self,
+    ) -> Result<
+        Environment<client::TestValidatorClientSync>,
+        ClientError<<client::TestValidatorClientSync as ClientSync>::ChannelError>,
+    > {
+        LogConfig::update_logger();
+        client::TestValidatorClientSync::new(self)
+    }
+
+    /// Returns the accounts that will be created when the environment is built,
+    /// including the SPL programs added by default.
     pub fn accounts(&self) -> &HashMap<Pubkey, AccountConfig> {
         &self.accounts
     }
+
+    /// Returns the address labels registered with [EnvironmentGenesis::add_address_label].
+    pub fn labels(&self) -> &HashMap<Pubkey, String> {
+        &self.address_labels
+    }
 }
 
 impl Default for EnvironmentGenesis {
@@ -199,6 +696,24 @@ impl Default for EnvironmentGenesis {
             address_labels: Default::default(),
             payer: None,
             log_config: None,
+            commitment: None,
+            confirm_commitment: None,
+            fees_enabled: false,
+            activated_features: Default::default(),
+            auto_advance_blockhash: false,
+            batched_writes: true,
+            write_chunk_size: DEFAULT_WRITE_CHUNK_SIZE,
+            timeout: None,
+            payer_balance_warning: None,
+            forbidden_clusters: Default::default(),
+            recent_logs_capacity: 16,
+            initial_slot: 0,
+            transaction_script: None,
+            loaders: vec![
+                solana_bpf_loader_program::solana_bpf_loader_deprecated_program!(),
+                solana_bpf_loader_program::solana_bpf_loader_program!(),
+                solana_bpf_loader_program::solana_bpf_loader_upgradeable_program!(),
+            ],
         };
 
         for (addr, account) in spl_programs(&Rent::default()) {
@@ -211,6 +726,7 @@ impl Default for EnvironmentGenesis {
                     data: account.data,
                     owner: account.owner,
                     executable: account.executable,
+                    rent_epoch: None,
                 },
             );
         }
@@ -246,11 +762,74 @@ impl Default for EnvironmentGenesis {
 
 pub struct Environment<C> {
     client: C,
-    _address_labels: HashMap<Pubkey, String>,
+    address_labels: HashMap<Pubkey, String>,
     payer: Keypair,
     /// Cached [Rent] information
     rent: Rent,
     log_config: LogConfig,
+    /// Blockhash used by the last transaction built by this environment
+    last_used_blockhash: Option<Hash>,
+    /// Whether to advance the blockhash if it did not change since the last transaction
+    auto_advance_blockhash: bool,
+    /// Whether to send loader write transactions in a batch
+    batched_writes: bool,
+    /// Size of the data chunk written by each loader write instruction
+    write_chunk_size: usize,
+    /// Payer balance below which a warning is logged after sending a transaction
+    payer_balance_warning: Option<u64>,
+    /// Compute units consumed by the successful transactions since the last reset
+    total_units_consumed: u64,
+    /// File that records the sent transactions
+    transaction_script: Option<fs::File>,
+    /// Number of transactions sent by this environment
+    transactions_sent: u64,
+    summary_reporter: SummaryReporter<C>,
+}
+
+impl<C> Drop for Environment<C> {
+    fn drop(&mut self) {
+        // The balances are only captured once a transaction is sent with the summary enabled
+        if self.log_config.log_summary_on_drop && self.summary_reporter.initial_balances.is_some() {
+            let summary = (self.summary_reporter.run_summary)(self);
+            info!("PoC Summary\n{}", summary);
+        }
+    }
+}
+
+/// Genesis hash of mainnet-beta cluster.
+const MAINNET_BETA_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+
+/// Default size of the data chunk written by each loader write instruction.
+///
+/// A transaction should fit in a single packet (`PACKET_DATA_SIZE`, 1232 bytes),
+/// and the remaining space is used by signatures, account keys, and the instruction header.
+const DEFAULT_WRITE_CHUNK_SIZE: usize = 900;
+
+/// Maximum size of the data chunk written by each loader write instruction.
+///
+/// A write transaction has at most two signatures (129 bytes), a message header and
+/// four account keys (132 bytes), a blockhash (32 bytes), and an instruction
+/// whose header and serialized arguments take 23 bytes besides the chunk.
+const MAX_WRITE_CHUNK_SIZE: usize = PACKET_DATA_SIZE - 316;
+
+/// Maximum number of associated token accounts created in a single transaction.
+///
+/// Each creation invokes the system program and the token program,
+/// so packing more of them risks exceeding the compute budget
+/// even if the transaction fits in a packet.
+const MAX_ASSOCIATED_TOKEN_ACCOUNTS_PER_TRANSACTION: usize = 4;
+
+/// Checks that the serialized transaction fits in a single packet,
+/// as larger transactions are rejected by the network.
+fn check_transaction_size<E: std::error::Error>(
+    transaction: &Transaction,
+) -> Result<(), ClientError<E>> {
+    // Signatures are prefixed with a compact length, which is a single byte below 128
+    let size = 1 + 64 * transaction.signatures.len() + transaction.message_data().len();
+    if size > PACKET_DATA_SIZE {
+        return Err(ClientError::TransactionTooLarge { size });
+    }
+    Ok(())
 }
 
 fn instructions_to_tx(
@@ -266,13 +845,96 @@ fn instructions_to_tx(
     Transaction::new(&signers_vec, message, latest_blockhash)
 }
 
+/// Derives the associated token account address for a mint owned by `token_program_id`.
+pub fn get_associated_token_address_with_program_id(
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            &owner.to_bytes(),
+            &token_program_id.to_bytes(),
+            &mint.to_bytes(),
+        ],
+        &spl_associated_token_account::id(),
+    )
+    .0
+}
+
 type ClientErrorSync<C> = client::ClientError<<C as ClientSync>::ChannelError>;
 
 impl<C> Environment<C> {
+    /// Returns the compute units consumed by the successful transactions
+    /// since the environment was built or [Self::reset_unit_counter] was called.
+    ///
+    /// Transactions whose consumed units are not reported by the client,
+    /// such as successful remote transactions, are not counted.
+    pub fn total_units_consumed(&self) -> u64 {
+        self.total_units_consumed
+    }
+
+    /// Resets the counter of [Self::total_units_consumed] to zero.
+    pub fn reset_unit_counter(&mut self) {
+        self.total_units_consumed = 0;
+    }
+
     pub fn client(&mut self) -> &mut C {
         &mut self.client
     }
 
+    /// Runs `f` with every transaction result logged, then restores the previous logging of transaction results.
+    ///
+    /// This is useful for inspecting a few suspicious transactions
+    /// without making the whole PoC verbose.
+    /// Other options of the log config, such as [LogConfig::log_summary_on_drop], are left untouched.
+    pub fn with_verbose_logs<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let previous = self.log_config;
+        self.log_config.log_successful_transaction = true;
+        self.log_config.log_failed_transaction = true;
+        let result = f(self);
+        self.log_config.log_successful_transaction = previous.log_successful_transaction;
+        self.log_config.log_failed_transaction = previous.log_failed_transaction;
+        result
+    }
+
+    /// Derives the associated token account address of `owner` for `mint`.
+    pub fn associated_token_address(&self, owner: Pubkey, mint: Pubkey) -> Pubkey {
+        get_associated_token_address(&owner, &mint)
+    }
+
+    /// Derives the associated token account address of `owner` for `mint`
+    /// owned by `token_program_id` (e.g., Token-2022).
+    pub fn associated_token_address_with_program_id(
+        &self,
+        owner: Pubkey,
+        mint: Pubkey,
+        token_program_id: Pubkey,
+    ) -> Pubkey {
+        get_associated_token_address_with_program_id(&owner, &mint, &token_program_id)
+    }
+
+    /// Returns the label of the address registered with [EnvironmentGenesis::add_address_label].
+    pub fn program_label(&self, address: Pubkey) -> Option<&str> {
+        self.address_labels.get(&address).map(String::as_str)
+    }
+
+    /// Returns whether the address is a builtin program, a sysvar,
+    /// or one of the SPL programs included in the genesis by default.
+    pub fn is_builtin(&self, address: Pubkey) -> bool {
+        sdk_ids::SDK_IDS.contains(&address)
+            || [
+                native_loader::ID,
+                stake::program::ID,
+                compute_budget::ID,
+                spl_token::ID,
+                spl_associated_token_account::ID,
+                spl_memo::v1::ID,
+                spl_memo::ID,
+            ]
+            .contains(&address)
+    }
+
     pub fn payer(&self) -> &Keypair {
         &self.payer
     }
@@ -281,9 +943,39 @@ impl<C> Environment<C> {
         self.rent
     }
 
+    /// Returns the rent-exempt minimum computed from the cached [Rent].
+    ///
+    /// Use [ClientSync::minimum_balance_for_rent_exemption] for the value reported by the network.
     pub fn rent_exemption_amount(&self, data_len: usize) -> u64 {
         self.rent.minimum_balance(data_len).max(1)
     }
+
+    /// Reconstructs the data of the `Instructions` sysvar that the program sees
+    /// while executing `instructions[current_index]` in a transaction paid by the payer.
+    ///
+    /// The runtime populates the sysvar for every transaction that references
+    /// `sysvar::instructions::id()`, but the account is never stored in the bank,
+    /// so it cannot be fetched with [Self::get_account] after the execution.
+    /// The returned data can be inspected with the functions in `sysvar::instructions`,
+    /// such as `load_current_index_checked` and `load_instruction_at_checked`.
+    pub fn instructions_sysvar_data(
+        &self,
+        instructions: &[Instruction],
+        current_index: u16,
+    ) -> Vec<u8> {
+        let message =
+            SanitizedMessage::try_from(Message::new(instructions, Some(&self.payer.pubkey())))
+                .expect("Invalid message");
+        let mut data =
+            sysvar::instructions::construct_instructions_data(&message.decompile_instructions());
+        sysvar::instructions::store_current_index(&mut data, current_index);
+        data
+    }
+
+    /// Returns the blockhash used by the last transaction built by this environment.
+    pub fn last_used_blockhash(&self) -> Option<Hash> {
+        self.last_used_blockhash
+    }
 }
 
 impl<C: ClientSync> Environment<C> {
@@ -293,105 +985,846 @@ impl<C: ClientSync> Environment<C> {
         instructions: &[Instruction],
         signers: &[&Keypair],
     ) -> Result<(), ClientErrorSync<C>> {
-        let blockhash = self.client.latest_blockhash()?;
+        let blockhash = self.blockhash_for_next_transaction()?;
         let transaction = instructions_to_tx(&self.payer, blockhash, instructions, signers);
-
-        match self.client.send_transaction(transaction) {
-            Ok(details) => {
-                if self.log_config.log_successful_transaction {
-                    info!("Successful Transaction\n{:#?}", details);
+        match self.send_transaction(transaction) {
+            // An identical transaction was already processed with the same blockhash,
+            // so retry once with a fresh blockhash
+            Err(err) if err.is_already_processed() => {
+                let result = self
+                    .client
+                    .tick_beyond(blockhash)
+                    .map_err(ClientError::ChannelError)
+                    .and_then(|blockhash| {
+                        self.last_used_blockhash = Some(blockhash);
+                        let transaction =
+                            instructions_to_tx(&self.payer, blockhash, instructions, signers);
+                        self.send_transaction(transaction)
+                    });
+                if let Err(err) = result {
+                    warn!(
+                        "Retrying an already processed transaction with a fresh blockhash failed: {}",
+                        err
+                    );
+                    return Err(err);
                 }
-                Ok(())
             }
-            Err(err) => {
-                if self.log_config.log_failed_transaction {
-                    warn!("Failed Transaction\n{:#?}", &err);
-                }
-                Err(err)
+            result => {
+                result?;
             }
         }
+        Ok(())
     }
 
-    /// Runs a single instruction as a transaction and returns the result.
-    pub fn run_instruction(
+    /// Executes the instruction as a transaction after checking it for duplicated accounts,
+    /// and returns the duplicated addresses.
+    ///
+    /// An account is flagged when it appears more than once in the account metas
+    /// and at least one of the occurrences is writable,
+    /// which breaks programs that assume the accounts are distinct.
+    /// Flagged accounts are logged as warnings before the execution,
+    /// so they are reported even if the transaction fails.
+    pub fn run_instruction_detect_dup(
         &mut self,
         instruction: Instruction,
         signers: &[&Keypair],
-    ) -> Result<(), ClientErrorSync<C>> {
+    ) -> Result<Vec<Pubkey>, ClientErrorSync<C>> {
+        let mut duplicates = Vec::new();
+        for (index, meta) in instruction.accounts.iter().enumerate() {
+            if duplicates.contains(&meta.pubkey) {
+                continue;
+            }
+            let mut occurrences = instruction.accounts[index..]
+                .iter()
+                .filter(|other| other.pubkey == meta.pubkey);
+            let count = occurrences.clone().count();
+            if count > 1 && occurrences.any(|other| other.is_writable) {
+                warn!(
+                    "Account {} appears {} times in the instruction with a writable occurrence",
+                    meta.pubkey, count
+                );
+                duplicates.push(meta.pubkey);
+            }
+        }
+
         self.run_instructions(&[instruction], signers)?;
-        Ok(())
+        Ok(duplicates)
     }
 
-    /// Executes provided instructions as a transaction and returns the result with the provided payer keypair.
-    pub fn run_instructions_with_payer(
+    /// Returns the fee that running the provided instructions as a transaction would cost,
+    /// without sending it.
+    pub fn estimate_fee(
         &mut self,
         instructions: &[Instruction],
-        payer: &Keypair,
         signers: &[&Keypair],
-    ) -> Result<(), ClientErrorSync<C>> {
+    ) -> Result<u64, ClientErrorSync<C>> {
         let blockhash = self.client.latest_blockhash()?;
-        let transaction = instructions_to_tx(payer, blockhash, instructions, signers);
-        self.client.send_transaction(transaction)?;
-        Ok(())
+        let transaction = instructions_to_tx(&self.payer, blockhash, instructions, signers);
+        self.client.get_fee_for_message(&transaction.message)
     }
 
-    /// Runs a single instruction as a transaction and returns the result with the provided payer keypair.
-    pub fn run_instruction_with_payer(
+    /// Executes provided instructions as a transaction with an SPL memo instruction prepended.
+    ///
+    /// The memo text appears in the transaction logs.
+    pub fn run_instructions_with_memo(
         &mut self,
-        instruction: Instruction,
-        payer: &Keypair,
+        instructions: &[Instruction],
+        memo: &str,
         signers: &[&Keypair],
     ) -> Result<(), ClientErrorSync<C>> {
-        self.run_instructions_with_payer(&[instruction], payer, signers)?;
-        Ok(())
+        let mut instructions_with_memo = vec![spl_memo::build_memo(memo.as_bytes(), &[])];
+        instructions_with_memo.extend_from_slice(instructions);
+        self.run_instructions(&instructions_with_memo, signers)
     }
 
-    /// Wait for the next tick
-    pub fn wait_for_tick(&mut self) -> Result<Hash, ClientErrorSync<C>> {
-        let current = self.client.latest_blockhash()?;
-        Ok(self.client.tick_beyond(current)?)
+    /// Executes provided instructions as a transaction pinned to the given blockhash
+    /// instead of the latest one and returns the result.
+    ///
+    /// This can be used to test the blockhash expiry window,
+    /// which results in `TransactionError::BlockhashNotFound` for an expired blockhash.
+    pub fn run_instructions_with_blockhash(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        blockhash: Hash,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.last_used_blockhash = Some(blockhash);
+        let transaction = instructions_to_tx(&self.payer, blockhash, instructions, signers);
+        self.send_transaction(transaction)?;
+        Ok(())
     }
 
-    /// Gets account information at the given address.
-    pub fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientErrorSync<C>> {
-        self.client.get_account(address)
+    /// Executes provided instructions as a transaction and returns the consumed compute units.
+    ///
+    /// Returns `ClientError::UnitsConsumedUnavailable` if the client does not report them,
+    /// which is the case for successful remote transactions.
+    pub fn run_instructions_units(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<u64, ClientErrorSync<C>> {
+        let blockhash = self.blockhash_for_next_transaction()?;
+        let transaction = instructions_to_tx(&self.payer, blockhash, instructions, signers);
+        self.send_transaction(transaction)?
+            .units_consumed
+            .ok_or(ClientError::UnitsConsumedUnavailable)
     }
 
-    /// Gets how much token does this token account hold.
-    pub fn token_balance(&mut self, token_account: Pubkey) -> Result<u64, ClientErrorSync<C>> {
-        let account = self.client.get_account(token_account)?;
-        Ok(spl_token::state::Account::unpack_unchecked(&account.data())
-            .map_err(|_| ClientError::InvalidAccountData(token_account))?
-            .amount)
+    /// Executes provided instructions as a transaction and
+    /// panics if it consumed more than `max_units` compute units.
+    pub fn run_and_assert_units(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        max_units: u64,
+    ) -> Result<(), ClientErrorSync<C>> {
+        let units_consumed = self.run_instructions_units(instructions, signers)?;
+        assert!(
+            units_consumed <= max_units,
+            "Transaction consumed {} compute units, which exceeds the limit of {}",
+            units_consumed,
+            max_units
+        );
+        Ok(())
     }
 
-    /// Creates a new token mint using the provided keypair.
+    /// Builds a transaction signed only by the payer,
+    /// which can be signed incrementally by the other signers.
+    pub fn partial_transaction(
+        &mut self,
+        instructions: &[Instruction],
+    ) -> Result<PartialTransaction, ClientErrorSync<C>> {
+        let blockhash = self.blockhash_for_next_transaction()?;
+        Ok(PartialTransaction::new(
+            &self.payer,
+            blockhash,
+            instructions,
+        ))
+    }
+
+    /// Sends a transaction built with [Self::partial_transaction] and returns the result.
+    ///
+    /// Returns `ClientError::InvalidTransaction(TransactionError::SignatureFailure)`
+    /// without sending it if any required signer has not signed the transaction.
+    pub fn send_partial_transaction(
+        &mut self,
+        transaction: PartialTransaction,
+    ) -> Result<(), ClientErrorSync<C>> {
+        if !transaction.is_fully_signed() {
+            warn!(
+                "Transaction is missing signatures from {:?}",
+                transaction.missing_signers()
+            );
+            return Err(ClientError::InvalidTransaction(
+                TransactionError::SignatureFailure,
+            ));
+        }
+
+        self.send_transaction(transaction.into_transaction())?;
+        Ok(())
+    }
+
+    /// Sends a transaction built outside of the environment and returns the result.
+    ///
+    /// The recent blockhash of the transaction is replaced with the latest one,
+    /// and the transaction is signed again by `signers` and the payer,
+    /// if the payer is one of the required signers.
+    /// Returns `ClientError::InvalidTransaction(TransactionError::SignatureFailure)`
+    /// without sending it if a required signer is missing or a signer is not required.
+    pub fn send_prebuilt(
+        &mut self,
+        mut transaction: Transaction,
+        signers: &[&Keypair],
+    ) -> Result<(), ClientErrorSync<C>> {
+        let blockhash = self.blockhash_for_next_transaction()?;
+
+        let num_required_signatures = transaction.message.header.num_required_signatures as usize;
+        let payer_required = transaction
+            .message
+            .account_keys
+            .iter()
+            .take(num_required_signatures)
+            .any(|address| *address == self.payer.pubkey());
+        let mut signers_vec = Vec::new();
+        if payer_required {
+            signers_vec.push(&self.payer);
+        }
+        signers_vec.extend_from_slice(signers);
+
+        if let Err(err) = transaction.try_sign(&signers_vec, blockhash) {
+            warn!("Failed to sign the transaction: {}", err);
+            return Err(ClientError::InvalidTransaction(
+                TransactionError::SignatureFailure,
+            ));
+        }
+
+        self.send_transaction(transaction)?;
+        Ok(())
+    }
+
+    /// Sends a caller-constructed transaction verbatim and returns the result.
+    ///
+    /// Unlike [Self::run_instructions], the transaction is neither signed nor modified,
+    /// so this can be used to send a transaction that deliberately lacks a required signature.
+    pub fn send_raw_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.send_transaction(transaction)?;
+        Ok(())
+    }
+
+    /// Fetches the blockhash for the next transaction and records it.
+    fn blockhash_for_next_transaction(&mut self) -> Result<Hash, ClientErrorSync<C>> {
+        let mut blockhash = self.client.latest_blockhash()?;
+        if self.auto_advance_blockhash && self.last_used_blockhash == Some(blockhash) {
+            blockhash = self.client.tick_beyond(blockhash)?;
+        }
+        self.last_used_blockhash = Some(blockhash);
+        Ok(blockhash)
+    }
+
+    /// Sends a transaction and logs the result according to the log configuration.
+    fn send_transaction(
+        &mut self,
+        transaction: Transaction,
+    ) -> Result<TransactionDetails, ClientErrorSync<C>> {
+        let mut details = self.send_transactions(vec![transaction])?;
+        Ok(details
+            .pop()
+            .expect("Details should exist for the sent transaction"))
+    }
+
+    /// Sends transactions and logs the result according to the log configuration.
+    ///
+    /// Every transaction sent by the environment goes through this function,
+    /// which records the transactions and updates the run summary and the payer balance warning.
+    /// Multiple transactions are handed to the client at once,
+    /// so that remote clients can confirm them together.
+    /// A transaction rejected as already processed was never executed,
+    /// so it is returned as an error without being recorded, which lets the caller retry it.
+    fn send_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+    ) -> Result<Vec<TransactionDetails>, ClientErrorSync<C>> {
+        for transaction in &transactions {
+            check_transaction_size(transaction)?;
+        }
+        self.before_send();
+
+        let count = transactions.len() as u64;
+        let result = match transactions.as_slice() {
+            [transaction] => self
+                .client
+                .send_transaction(transaction.clone())
+                .map(|details| vec![details]),
+            _ => self.client.send_transactions(transactions.clone()),
+        };
+        if count == 1 && matches!(&result, Err(err) if err.is_already_processed()) {
+            return result;
+        }
+
+        for transaction in &transactions {
+            self.record_transaction(transaction);
+        }
+        match &result {
+            Ok(details) => {
+                for details in details {
+                    if self.log_config.log_successful_transaction {
+                        info!("Successful Transaction\n{:#?}", details);
+                    }
+                    self.total_units_consumed += details.units_consumed.unwrap_or(0);
+                }
+            }
+            Err(err) => {
+                if self.log_config.log_failed_transaction {
+                    warn!("Failed Transaction\n{:#?}", err);
+                }
+            }
+        }
+        self.check_payer_balance();
+        self.transactions_sent += count;
+        result
+    }
+
+    /// Appends the transaction to the transaction script if it is enabled.
+    fn record_transaction(&mut self, transaction: &Transaction) {
+        if let Some(file) = &mut self.transaction_script {
+            // Failing to record should not affect the PoC itself
+            if let Err(err) = script::write_entry(file, transaction, &self.address_labels) {
+                warn!("Failed to record the transaction to the script: {}", err);
+            }
+        }
+    }
+
+    /// Captures the balances of the labeled accounts before the first transaction
+    /// if the run summary is enabled.
+    fn before_send(&mut self) {
+        if self.log_config.log_summary_on_drop && self.summary_reporter.initial_balances.is_none() {
+            let balances = self.labeled_balances();
+            self.summary_reporter.initial_balances = Some(balances);
+        }
+    }
+
+    /// Returns the balances of the labeled accounts, skipping the ones that cannot be read.
+    fn labeled_balances(&mut self) -> HashMap<Pubkey, u64> {
+        let addresses: Vec<_> = self.address_labels.keys().copied().collect();
+        addresses
+            .into_iter()
+            .filter_map(|address| Some((address, self.lamports_or_zero(address).ok()?)))
+            .collect()
+    }
+
+    /// Returns the statistics of the transactions sent by this environment.
+    ///
+    /// The lamport changes of the labeled accounts are only tracked
+    /// when [LogConfig::log_summary_on_drop] is enabled,
+    /// in which case the summary is also logged when the environment is dropped.
+    pub fn run_summary(&mut self) -> RunSummary {
+        let mut lamport_changes = Vec::new();
+        if let Some(initial_balances) = self.summary_reporter.initial_balances.clone() {
+            let current_balances = self.labeled_balances();
+            for (address, initial) in initial_balances {
+                if let Some(&current) = current_balances.get(&address) {
+                    let change = current as i128 - initial as i128;
+                    if change != 0 {
+                        lamport_changes.push((self.address_labels[&address].clone(), change));
+                    }
+                }
+            }
+            lamport_changes.sort();
+        }
+
+        RunSummary {
+            transactions_sent: self.transactions_sent,
+            total_units_consumed: self.total_units_consumed,
+            lamport_changes,
+        }
+    }
+
+    /// Logs a warning if the payer balance is below the configured threshold.
+    fn check_payer_balance(&mut self) {
+        if let Some(threshold) = self.payer_balance_warning {
+            // Failing to read the balance should not hide the result of the transaction
+            if let Ok(balance) = self.payer_balance() {
+                if balance < threshold {
+                    warn!(
+                        "Payer balance {} is below the warning threshold {}",
+                        balance, threshold
+                    );
+                }
+            }
+        }
+    }
+
+    /// Waits until a previously sent transaction is confirmed and returns its details.
+    pub fn confirm_transaction(
+        &mut self,
+        signature: Signature,
+    ) -> Result<TransactionDetails, ClientErrorSync<C>> {
+        self.client.confirm_transaction(signature)
+    }
+
+    /// Runs a single instruction as a transaction and returns the result.
+    pub fn run_instruction(
+        &mut self,
+        instruction: Instruction,
+        signers: &[&Keypair],
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instructions(&[instruction], signers)?;
+        Ok(())
+    }
+
+    /// Runs a single instruction as a transaction with `extra_accounts` appended to its account metas.
+    ///
+    /// A program can only access the accounts passed to its instruction,
+    /// so this makes accounts that the instruction builder does not reference available
+    /// to the program as trailing (remaining) accounts,
+    /// with the signer and writable flags of each meta.
+    pub fn run_instruction_with_extra_accounts(
+        &mut self,
+        mut instruction: Instruction,
+        extra_accounts: &[AccountMeta],
+        signers: &[&Keypair],
+    ) -> Result<(), ClientErrorSync<C>> {
+        instruction.accounts.extend_from_slice(extra_accounts);
+        self.run_instruction(instruction, signers)
+    }
+
+    /// Executes provided instructions as a transaction and returns the result with the provided payer keypair.
+    ///
+    /// The fee payer can differ from both the environment payer and the instruction signers,
+    /// which models a sponsor paying the fees of a transaction signed by others.
+    /// The fee payer may also be listed in `signers`, in which case it signs only once.
+    pub fn run_instructions_with_payer(
+        &mut self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), ClientErrorSync<C>> {
+        let blockhash = self.blockhash_for_next_transaction()?;
+        let transaction = instructions_to_tx(payer, blockhash, instructions, signers);
+        self.send_transaction(transaction)?;
+        Ok(())
+    }
+
+    /// Runs a single instruction as a transaction and returns the result with the provided payer keypair.
+    pub fn run_instruction_with_payer(
+        &mut self,
+        instruction: Instruction,
+        payer: &Keypair,
+        signers: &[&Keypair],
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instructions_with_payer(&[instruction], payer, signers)?;
+        Ok(())
+    }
+
+    /// Forces the client to produce a new blockhash and returns it.
+    ///
+    /// On local environment, this registers ticks to the bank until the blockhash changes.
+    /// On remote environment, this waits until the cluster produces a new blockhash.
+    pub fn new_blockhash(&mut self) -> Result<Hash, ClientErrorSync<C>> {
+        self.wait_for_tick()
+    }
+
+    /// Wait for the next tick
+    pub fn wait_for_tick(&mut self) -> Result<Hash, ClientErrorSync<C>> {
+        let current = self.client.latest_blockhash()?;
+        Ok(self.client.tick_beyond(current)?)
+    }
+
+    /// Gets `length` bytes of the account data starting at `offset`.
+    ///
+    /// On remote environment, only the requested bytes are transferred,
+    /// which is useful for reading the header of a large account.
+    pub fn get_account_slice(
+        &mut self,
+        address: Pubkey,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, ClientErrorSync<C>> {
+        self.client.get_account_data_slice(address, offset, length)
+    }
+
+    /// Gets the lamport balance of the payer.
+    pub fn payer_balance(&mut self) -> Result<u64, ClientErrorSync<C>> {
+        Ok(self.client.get_account(self.payer.pubkey())?.lamports)
+    }
+
+    /// Gets account information at the given address.
+    pub fn get_account(&mut self, address: Pubkey) -> Result<Account, ClientErrorSync<C>> {
+        self.client.get_account(address)
+    }
+
+    /// Gets account information at the given address,
+    /// checking that the account is owned by `expected_owner`.
+    ///
+    /// Returns `ClientError::WrongOwner` if the owner does not match.
+    pub fn get_account_owned_by(
+        &mut self,
+        address: Pubkey,
+        expected_owner: Pubkey,
+    ) -> Result<Account, ClientErrorSync<C>> {
+        let account = self.client.get_account(address)?;
+        if account.owner != expected_owner {
+            return Err(ClientError::WrongOwner {
+                address,
+                expected: expected_owner,
+                actual: account.owner,
+            });
+        }
+        Ok(account)
+    }
+
+    /// Checks whether the account at the given address holds enough lamports to be rent-exempt.
+    pub fn is_rent_exempt(&mut self, address: Pubkey) -> Result<bool, ClientErrorSync<C>> {
+        let account = self.client.get_account(address)?;
+        Ok(self.rent.is_exempt(account.lamports, account.data.len()))
+    }
+
+    /// Returns `ClientError::NotRentExempt` if the account at the given address is not rent-exempt.
+    pub fn assert_rent_exempt(&mut self, address: Pubkey) -> Result<(), ClientErrorSync<C>> {
+        if !self.is_rent_exempt(address)? {
+            return Err(ClientError::NotRentExempt(address));
+        }
+        Ok(())
+    }
+
+    /// Gets the current slot from the `Clock` sysvar.
+    ///
+    /// On local environment, the clock stays constant unless the bank is advanced
+    /// with [Environment::warp_to_slot].
+    /// Registering ticks for a new blockhash does not update the `Clock` sysvar.
+    pub fn slot(&mut self) -> Result<Slot, ClientErrorSync<C>> {
+        Ok(self.client.get_sysvar::<Clock>()?.slot)
+    }
+
+    /// Gets the current unix timestamp from the `Clock` sysvar.
+    ///
+    /// On local environment, the timestamp stays constant as described in [Self::slot].
+    pub fn unix_timestamp(&mut self) -> Result<UnixTimestamp, ClientErrorSync<C>> {
+        Ok(self.client.get_sysvar::<Clock>()?.unix_timestamp)
+    }
+
+    /// Gets the `SlotHashes` sysvar.
+    ///
+    /// Returns `ClientError::AccountNotFound` if the sysvar account is not available,
+    /// which can happen on remote RPC nodes that do not serve sysvar accounts.
+    pub fn slot_hashes(&mut self) -> Result<SlotHashes, ClientErrorSync<C>> {
+        self.client.get_sysvar::<SlotHashes>()
+    }
+
+    /// Gets the `StakeHistory` sysvar.
+    ///
+    /// Returns `ClientError::AccountNotFound` if the sysvar account is not available,
+    /// which can happen on remote RPC nodes that do not serve sysvar accounts.
+    pub fn stake_history(&mut self) -> Result<StakeHistory, ClientErrorSync<C>> {
+        self.client.get_sysvar::<StakeHistory>()
+    }
+
+    /// Gets the `EpochSchedule` sysvar.
+    pub fn epoch_schedule(&mut self) -> Result<EpochSchedule, ClientErrorSync<C>> {
+        self.client.get_sysvar::<EpochSchedule>()
+    }
+
+    /// Gets how much token does this token account hold.
+    pub fn token_balance(&mut self, token_account: Pubkey) -> Result<u64, ClientErrorSync<C>> {
+        Ok(self.token_account(token_account)?.amount)
+    }
+
+    /// Gets how much token does this account hold without checking that it is
+    /// an initialized token account.
+    ///
+    /// Prefer [Environment::token_balance] unless the loose behavior is required.
+    pub fn token_balance_unchecked(
+        &mut self,
+        token_account: Pubkey,
+    ) -> Result<u64, ClientErrorSync<C>> {
+        let account = self.client.get_account(token_account)?;
+        Ok(spl_token::state::Account::unpack_unchecked(&account.data())
+            .map_err(|_| ClientError::InvalidAccountData(token_account))?
+            .amount)
+    }
+
+    /// Gets the unpacked state of a token account, including its owner, delegate,
+    /// and close authority.
+    ///
+    /// Returns `ClientError::WrongOwner` if the account is not owned by the token program,
+    /// and `ClientError::InvalidAccountData` if the account is not an initialized token account.
+    pub fn token_account(
+        &mut self,
+        token_account: Pubkey,
+    ) -> Result<spl_token::state::Account, ClientErrorSync<C>> {
+        let account = self.get_account_owned_by(token_account, spl_token::ID)?;
+        spl_token::state::Account::unpack(&account.data())
+            .map_err(|_| ClientError::InvalidAccountData(token_account))
+    }
+
+    /// Gets the unpacked state of a token mint.
+    ///
+    /// Returns `ClientError::WrongOwner` if the account is not owned by the token program,
+    /// and `ClientError::InvalidAccountData` if the account is not an initialized mint.
+    pub fn token_mint(
+        &mut self,
+        mint: Pubkey,
+    ) -> Result<spl_token::state::Mint, ClientErrorSync<C>> {
+        let account = self.get_account_owned_by(mint, spl_token::ID)?;
+        spl_token::state::Mint::unpack(&account.data())
+            .map_err(|_| ClientError::InvalidAccountData(mint))
+    }
+
+    /// Gets the total supply of the token mint.
+    pub fn mint_supply(&mut self, mint: Pubkey) -> Result<u64, ClientErrorSync<C>> {
+        Ok(self.token_mint(mint)?.supply)
+    }
+
+    /// Converts a UI amount (e.g., `1.5` tokens) into a raw amount using the decimals of `mint`.
+    pub fn ui_amount_to_amount(
+        &mut self,
+        mint: Pubkey,
+        ui_amount: f64,
+    ) -> Result<u64, ClientErrorSync<C>> {
+        let decimals = self.token_mint(mint)?.decimals;
+        Ok(spl_token::ui_amount_to_amount(ui_amount, decimals))
+    }
+
+    /// Converts a raw amount into a UI amount using the decimals of `mint`.
+    pub fn amount_to_ui_amount(
+        &mut self,
+        mint: Pubkey,
+        amount: u64,
+    ) -> Result<f64, ClientErrorSync<C>> {
+        let decimals = self.token_mint(mint)?.decimals;
+        Ok(spl_token::amount_to_ui_amount(amount, decimals))
+    }
+
+    /// Checks that the balances of `holders` add up to the supply of `mint`.
+    ///
+    /// `holders` should list every token account of the mint.
+    /// Returns `ClientError::SupplyMismatch` if the sum differs from the supply,
+    /// which indicates that tokens were created or destroyed outside of mint and burn.
+    pub fn assert_supply_matches(
+        &mut self,
+        mint: Pubkey,
+        holders: &[Pubkey],
+    ) -> Result<(), ClientErrorSync<C>> {
+        let supply = self.mint_supply(mint)?;
+        let mut total = 0u128;
+        for &holder in holders {
+            total += self.token_balance(holder)? as u128;
+        }
+
+        if total != supply as u128 {
+            return Err(ClientError::SupplyMismatch {
+                mint,
+                supply,
+                total,
+            });
+        }
+        Ok(())
+    }
+
+    /// Gets all token accounts owned by `owner`, optionally filtered by `mint`.
+    pub fn get_token_accounts_by_owner(
+        &mut self,
+        owner: Pubkey,
+        mint: Option<Pubkey>,
+    ) -> Result<Vec<(Pubkey, spl_token::state::Account)>, ClientErrorSync<C>> {
+        // Mint and owner are stored at offset 0 and 32 of the token account
+        let mut filters = vec![
+            AccountFilter::DataSize(spl_token::state::Account::LEN as u64),
+            AccountFilter::Memcmp {
+                offset: 32,
+                bytes: owner.to_bytes().to_vec(),
+            },
+        ];
+        if let Some(mint) = mint {
+            filters.push(AccountFilter::Memcmp {
+                offset: 0,
+                bytes: mint.to_bytes().to_vec(),
+            });
+        }
+
+        self.client
+            .get_program_accounts(spl_token::ID, &filters)?
+            .into_iter()
+            .map(|(address, account)| {
+                spl_token::state::Account::unpack(&account.data)
+                    .map(|token_account| (address, token_account))
+                    .map_err(|_| ClientError::InvalidAccountData(address))
+            })
+            .collect()
+    }
+
+    /// Gets all token accounts of `mint` with their balances.
+    pub fn token_holders(
+        &mut self,
+        mint: Pubkey,
+    ) -> Result<Vec<(Pubkey, u64)>, ClientErrorSync<C>> {
+        // Mint is stored at offset 0 of the token account
+        let filters = [
+            AccountFilter::DataSize(spl_token::state::Account::LEN as u64),
+            AccountFilter::Memcmp {
+                offset: 0,
+                bytes: mint.to_bytes().to_vec(),
+            },
+        ];
+
+        self.client
+            .get_program_accounts(spl_token::ID, &filters)?
+            .into_iter()
+            .map(|(address, account)| {
+                spl_token::state::Account::unpack(&account.data)
+                    .map(|token_account| (address, token_account.amount))
+                    .map_err(|_| ClientError::InvalidAccountData(address))
+            })
+            .collect()
+    }
+
+    /// Gets the mint authority of the token mint. `None` means that no more tokens can be minted.
+    pub fn mint_authority(&mut self, mint: Pubkey) -> Result<Option<Pubkey>, ClientErrorSync<C>> {
+        Ok(self.token_mint(mint)?.mint_authority.into())
+    }
+
+    /// Gets the freeze authority of the token mint. `None` means that accounts cannot be frozen.
+    pub fn mint_freeze_authority(
+        &mut self,
+        mint: Pubkey,
+    ) -> Result<Option<Pubkey>, ClientErrorSync<C>> {
+        Ok(self.token_mint(mint)?.freeze_authority.into())
+    }
+
+    /// Changes the mint authority of the token mint, signed by the current authority.
+    /// Setting it to `None` permanently disables minting.
+    pub fn set_mint_authority(
+        &mut self,
+        mint: Pubkey,
+        current: &Keypair,
+        new: Option<Pubkey>,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.set_mint_authority_of_type(mint, AuthorityType::MintTokens, current, new)
+    }
+
+    /// Changes the freeze authority of the token mint, signed by the current authority.
+    /// Setting it to `None` permanently disables freezing.
+    pub fn set_freeze_authority(
+        &mut self,
+        mint: Pubkey,
+        current: &Keypair,
+        new: Option<Pubkey>,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.set_mint_authority_of_type(mint, AuthorityType::FreezeAccount, current, new)
+    }
+
+    fn set_mint_authority_of_type(
+        &mut self,
+        mint: Pubkey,
+        authority_type: AuthorityType,
+        current: &Keypair,
+        new: Option<Pubkey>,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instruction(
+            spl_token::instruction::set_authority(
+                &spl_token::ID,
+                &mint,
+                new.as_ref(),
+                authority_type,
+                &current.pubkey(),
+                &[],
+            )
+            .unwrap(),
+            &[current],
+        )?;
+        Ok(())
+    }
+
+    /// Creates a new token mint using the provided keypair.
     pub fn create_token_mint(
         &mut self,
-        mint: &Keypair,
-        authority: Pubkey,
-        freeze_authority: Option<Pubkey>,
-        decimals: u8,
+        mint: &Keypair,
+        authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+        decimals: u8,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instructions(
+            &[
+                system_instruction::create_account(
+                    &self.payer.pubkey(),
+                    &mint.pubkey(),
+                    self.rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_mint(
+                    &spl_token::ID,
+                    &mint.pubkey(),
+                    &authority,
+                    freeze_authority.as_ref(),
+                    decimals,
+                )
+                .unwrap(),
+            ],
+            &[mint],
+        )?;
+        Ok(())
+    }
+
+    /// Creates a new token mint with the payer as the mint authority
+    /// and mints `amount` tokens to the associated token account of `supply_owner`.
+    ///
+    /// Returns the address of the associated token account.
+    pub fn create_mint_with_supply(
+        &mut self,
+        mint: &Keypair,
+        decimals: u8,
+        supply_owner: Pubkey,
+        amount: u64,
+    ) -> Result<Pubkey, ClientErrorSync<C>> {
+        let authority = self.payer.pubkey();
+        self.create_token_mint(mint, authority, None, decimals)?;
+
+        let address = self.get_or_create_associated_token_account(supply_owner, mint.pubkey())?;
+        // The payer signs every transaction, so no additional signer is needed
+        self.run_instruction(
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &mint.pubkey(),
+                &address,
+                &authority,
+                &[],
+                amount,
+            )
+            .unwrap(),
+            &[],
+        )?;
+        Ok(address)
+    }
+
+    /// Creates a new token account at the address of the provided keypair.
+    pub fn create_token_account(
+        &mut self,
+        account: &Keypair,
+        mint: Pubkey,
+        owner: Pubkey,
     ) -> Result<(), ClientErrorSync<C>> {
         self.run_instructions(
             &[
                 system_instruction::create_account(
                     &self.payer.pubkey(),
-                    &mint.pubkey(),
-                    self.rent.minimum_balance(spl_token::state::Mint::LEN),
-                    spl_token::state::Mint::LEN as u64,
+                    &account.pubkey(),
+                    self.rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
                     &spl_token::ID,
                 ),
-                spl_token::instruction::initialize_mint(
+                spl_token::instruction::initialize_account(
                     &spl_token::ID,
-                    &mint.pubkey(),
-                    &authority,
-                    freeze_authority.as_ref(),
-                    decimals,
+                    &account.pubkey(),
+                    &mint,
+                    &owner,
                 )
                 .unwrap(),
             ],
-            &[mint],
+            &[account],
         )?;
         Ok(())
     }
@@ -419,6 +1852,170 @@ impl<C: ClientSync> Environment<C> {
         Ok(())
     }
 
+    /// Mints tokens to `recipient` token account with a multisig token authority.
+    ///
+    /// `signers` should contain at least `m` of the multisig signer keypairs.
+    pub fn mint_tokens_multisig(
+        &mut self,
+        mint: Pubkey,
+        multisig: Pubkey,
+        signers: &[&Keypair],
+        recipient: Pubkey,
+        amount: u64,
+    ) -> Result<(), ClientErrorSync<C>> {
+        let signer_pubkeys: Vec<_> = signers.iter().map(|signer| signer.pubkey()).collect();
+        self.run_instruction(
+            spl_token::instruction::mint_to(
+                &spl_token::ID,
+                &mint,
+                &recipient,
+                &multisig,
+                &signer_pubkeys.iter().collect::<Vec<_>>(),
+                amount,
+            )
+            .unwrap(),
+            signers,
+        )?;
+        Ok(())
+    }
+
+    /// Transfers tokens from `source` to `destination` token account with the owner keypair.
+    pub fn transfer_tokens(
+        &mut self,
+        source: Pubkey,
+        authority: &Keypair,
+        destination: Pubkey,
+        amount: u64,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instruction(
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &source,
+                &destination,
+                &authority.pubkey(),
+                &[],
+                amount,
+            )
+            .unwrap(),
+            &[authority],
+        )?;
+        Ok(())
+    }
+
+    /// Closes a token account and sends its lamports to `destination`,
+    /// which can be any account including one that is not owned by the account owner.
+    pub fn close_token_account(
+        &mut self,
+        account: Pubkey,
+        destination: Pubkey,
+        authority: &Keypair,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instruction(
+            spl_token::instruction::close_account(
+                &spl_token::ID,
+                &account,
+                &destination,
+                &authority.pubkey(),
+                &[],
+            )
+            .unwrap(),
+            &[authority],
+        )?;
+        Ok(())
+    }
+
+    /// Closes a token account like [Self::close_token_account]
+    /// and checks that `destination` received exactly the lamports of the closed account.
+    ///
+    /// Returns `ClientError::RentNotReclaimed` if the balance change of `destination` differs.
+    /// `destination` should not be the payer, whose balance also changes by the transaction fee.
+    pub fn close_token_account_expect_rent_to(
+        &mut self,
+        account: Pubkey,
+        destination: Pubkey,
+        authority: &Keypair,
+    ) -> Result<(), ClientErrorSync<C>> {
+        let expected = self.client.get_account(account)?.lamports;
+        let before = self.lamports_or_zero(destination)?;
+
+        self.close_token_account(account, destination, authority)?;
+
+        let actual = self.lamports_or_zero(destination)?.saturating_sub(before);
+        if actual != expected {
+            return Err(ClientError::RentNotReclaimed {
+                destination,
+                expected,
+                actual,
+            });
+        }
+        Ok(())
+    }
+
+    /// Returns the lamports of the account, treating a missing account as zero lamports.
+    fn lamports_or_zero(&mut self, address: Pubkey) -> Result<u64, ClientErrorSync<C>> {
+        match self.client.get_account(address) {
+            Ok(account) => Ok(account.lamports),
+            Err(ClientError::AccountNotFound(_)) => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Transfers tokens from `source` to `destination` token account with a multisig owner.
+    ///
+    /// `signers` should contain at least `m` of the multisig signer keypairs.
+    pub fn transfer_tokens_multisig(
+        &mut self,
+        source: Pubkey,
+        multisig: Pubkey,
+        signers: &[&Keypair],
+        destination: Pubkey,
+        amount: u64,
+    ) -> Result<(), ClientErrorSync<C>> {
+        let signer_pubkeys: Vec<_> = signers.iter().map(|signer| signer.pubkey()).collect();
+        self.run_instruction(
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &source,
+                &destination,
+                &multisig,
+                &signer_pubkeys.iter().collect::<Vec<_>>(),
+                amount,
+            )
+            .unwrap(),
+            signers,
+        )?;
+        Ok(())
+    }
+
+    /// Creates a new `m`-of-n token multisig account using the provided keypair.
+    pub fn create_multisig(
+        &mut self,
+        account: &Keypair,
+        signers: &[Pubkey],
+        m: u8,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instructions(
+            &[
+                system_instruction::create_account(
+                    &self.payer.pubkey(),
+                    &account.pubkey(),
+                    self.rent.minimum_balance(spl_token::state::Multisig::LEN),
+                    spl_token::state::Multisig::LEN as u64,
+                    &spl_token::ID,
+                ),
+                spl_token::instruction::initialize_multisig(
+                    &spl_token::ID,
+                    &account.pubkey(),
+                    &signers.iter().collect::<Vec<_>>(),
+                    m,
+                )
+                .unwrap(),
+            ],
+            &[account],
+        )?;
+        Ok(())
+    }
+
     /// Creates a token account using the associated token account scheme.
     pub fn create_associated_token_account(
         &mut self,
@@ -438,13 +2035,220 @@ impl<C: ClientSync> Environment<C> {
         Ok(get_associated_token_address(&owner, &mint))
     }
 
+    /// Creates associated token accounts for many `(owner, mint)` pairs,
+    /// packing the instructions into as few transactions as possible.
+    ///
+    /// Each transaction creates at most four accounts to stay within the compute budget.
+    /// None of the accounts should exist beforehand.
+    /// Returns the addresses of the associated token accounts in the order of `pairs`.
+    pub fn create_associated_token_accounts(
+        &mut self,
+        pairs: &[(Pubkey, Pubkey)],
+    ) -> Result<Vec<Pubkey>, ClientErrorSync<C>> {
+        let payer = self.payer.pubkey();
+        let mut batch: Vec<Instruction> = Vec::new();
+        for &(owner, mint) in pairs {
+            // We need this deprecated version of API for our target version of Solana
+            #[allow(deprecated)]
+            let instruction = spl_associated_token_account::create_associated_token_account(
+                &payer, &owner, &mint,
+            );
+
+            batch.push(instruction);
+            let message = Message::new(&batch, Some(&payer));
+            // Only the payer signs, which takes a compact length byte and a signature
+            let transaction_size = 1 + 64 + message.serialize().len();
+            if (transaction_size > PACKET_DATA_SIZE
+                || batch.len() > MAX_ASSOCIATED_TOKEN_ACCOUNTS_PER_TRANSACTION)
+                && batch.len() > 1
+            {
+                let instruction = batch.pop().unwrap();
+                self.run_instructions(&batch, &[])?;
+                batch = vec![instruction];
+            }
+        }
+        if !batch.is_empty() {
+            self.run_instructions(&batch, &[])?;
+        }
+
+        Ok(pairs
+            .iter()
+            .map(|(owner, mint)| get_associated_token_address(owner, mint))
+            .collect())
+    }
+
+    /// Creates a token account using the associated token account scheme
+    /// for a mint owned by `token_program_id` (e.g., Token-2022).
+    ///
+    /// Other token programs are only supported by remote environments.
+    /// The associated token account program 1.0.x shipped with the local bank
+    /// always creates the account through the classic SPL Token program,
+    /// and Token-2022 is not loaded locally, so local environments only accept `spl_token::ID`.
+    pub fn create_associated_token_account_with_program_id(
+        &mut self,
+        owner: Pubkey,
+        mint: Pubkey,
+        token_program_id: Pubkey,
+    ) -> Result<Pubkey, ClientErrorSync<C>> {
+        let address =
+            get_associated_token_address_with_program_id(&owner, &mint, &token_program_id);
+
+        // The builder that takes a token program requires a newer version of Solana,
+        // so we build the instruction by hand. The trailing rent sysvar is required
+        // by older versions of the associated token account program.
+        self.run_instruction(
+            Instruction {
+                program_id: spl_associated_token_account::id(),
+                accounts: vec![
+                    AccountMeta::new(self.payer.pubkey(), true),
+                    AccountMeta::new(address, false),
+                    AccountMeta::new_readonly(owner, false),
+                    AccountMeta::new_readonly(mint, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                    AccountMeta::new_readonly(token_program_id, false),
+                    AccountMeta::new_readonly(sysvar::rent::id(), false),
+                ],
+                data: Vec::new(),
+            },
+            &[],
+        )?;
+        Ok(address)
+    }
+
+    /// Returns the associated token account of `owner` for `mint`, creating it only if it does not exist yet.
+    pub fn get_or_create_associated_token_account(
+        &mut self,
+        owner: Pubkey,
+        mint: Pubkey,
+    ) -> Result<Pubkey, ClientErrorSync<C>> {
+        let address = get_associated_token_address(&owner, &mint);
+        match self.client.get_account(address) {
+            Ok(_) => Ok(address),
+            Err(ClientError::AccountNotFound(_)) => {
+                self.create_associated_token_account(owner, mint)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Mints tokens to the associated token account of `owner`,
+    /// creating the account first if it does not exist.
+    ///
+    /// Returns the address of the associated token account.
+    pub fn mint_to_new_account(
+        &mut self,
+        mint: Pubkey,
+        authority: &Keypair,
+        owner: Pubkey,
+        amount: u64,
+    ) -> Result<Pubkey, ClientErrorSync<C>> {
+        let address = self.get_or_create_associated_token_account(owner, mint)?;
+        self.mint_tokens(mint, authority, address, amount)?;
+        Ok(address)
+    }
+
+    /// Mints tokens to the associated token account of each owner,
+    /// creating the accounts that do not exist.
+    ///
+    /// Returns the addresses of the associated token accounts in the order of `distribution`.
+    pub fn distribute_tokens(
+        &mut self,
+        mint: Pubkey,
+        authority: &Keypair,
+        distribution: &[(Pubkey, u64)],
+    ) -> Result<Vec<Pubkey>, ClientErrorSync<C>> {
+        distribution
+            .iter()
+            .map(|&(owner, amount)| self.mint_to_new_account(mint, authority, owner, amount))
+            .collect()
+    }
+
+    /// Creates a rent-exempt account with `space` bytes of zeroed data owned by `owner`.
+    ///
+    /// Only the owning program can modify the data of an account,
+    /// so the data should be populated with an instruction of `owner` afterwards
+    /// (e.g., `initialize_account` for the token program).
+    /// Use [Self::create_account_with_data] to create an account with pre-filled data
+    /// that does not need to be owned by a specific program.
+    pub fn create_account(
+        &mut self,
+        account: &Keypair,
+        owner: Pubkey,
+        space: usize,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instruction(
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &account.pubkey(),
+                self.rent.minimum_balance(space),
+                space as u64,
+                &owner,
+            ),
+            &[account],
+        )?;
+        Ok(())
+    }
+
+    /// Creates a rent-exempt account with `space` bytes owned by `owner`
+    /// and initializes it with `init_instruction` in the same transaction.
+    ///
+    /// Creating and initializing an account atomically prevents others from
+    /// initializing the account in between, unlike [Self::create_account] followed by
+    /// a separate initialization transaction.
+    pub fn create_and_init(
+        &mut self,
+        account: &Keypair,
+        owner: Pubkey,
+        space: usize,
+        init_instruction: Instruction,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instructions(
+            &[
+                system_instruction::create_account(
+                    &self.payer.pubkey(),
+                    &account.pubkey(),
+                    self.rent.minimum_balance(space),
+                    space as u64,
+                    &owner,
+                ),
+                init_instruction,
+            ],
+            &[account],
+        )
+    }
+
+    /// Assigns a system account to `new_owner`, signed by the account.
+    ///
+    /// The account should be owned by the system program and have no data.
+    pub fn assign_account(
+        &mut self,
+        account: &Keypair,
+        new_owner: Pubkey,
+    ) -> Result<(), ClientErrorSync<C>> {
+        self.run_instruction(
+            system_instruction::assign(&account.pubkey(), &new_owner),
+            &[account],
+        )?;
+        Ok(())
+    }
+
     /// Creates an account with a data field.
     /// The account is required to be empty and will be owned by bpf_loader afterwards.
+    ///
+    /// Returns `ClientError::AccountDataTooLarge` without sending any transaction
+    /// if the data exceeds the maximum account size.
     pub fn create_account_with_data(
         &mut self,
         account: &Keypair,
         data: &[u8],
     ) -> Result<(), ClientErrorSync<C>> {
+        if data.len() > MAX_PERMITTED_DATA_LENGTH as usize {
+            return Err(ClientError::AccountDataTooLarge {
+                size: data.len(),
+                max: MAX_PERMITTED_DATA_LENGTH as usize,
+            });
+        }
+
         self.run_instruction(
             system_instruction::create_account(
                 &self.payer.pubkey(),
@@ -456,18 +2260,43 @@ impl<C: ClientSync> Environment<C> {
             &[account],
         )?;
 
-        let mut offset = 0usize;
-        for chunk in data.chunks(900) {
-            self.run_instruction(
-                loader_instruction::write(
-                    &account.pubkey(),
-                    &bpf_loader::id(),
-                    offset as u32,
-                    chunk.to_vec(),
-                ),
-                &[account],
-            )?;
-            offset += chunk.len();
+        self.write_chunks(data, account, |offset, chunk| {
+            loader_instruction::write(&account.pubkey(), &bpf_loader::id(), offset, chunk)
+        })?;
+
+        Ok(())
+    }
+
+    /// Writes `data` in chunks with the loader write instructions built by `write_instruction`.
+    ///
+    /// With batched writes, every write transaction is built upfront with the same blockhash
+    /// and handed to the client at once, which lets remote clients confirm them together
+    /// instead of waiting for a confirmation per chunk.
+    /// Otherwise, each chunk is sent and confirmed one by one.
+    fn write_chunks(
+        &mut self,
+        data: &[u8],
+        signer: &Keypair,
+        write_instruction: impl Fn(u32, Vec<u8>) -> Instruction,
+    ) -> Result<(), ClientErrorSync<C>> {
+        let chunk_size = self.write_chunk_size;
+        let instructions = data
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(index, chunk)| write_instruction((index * chunk_size) as u32, chunk.to_vec()));
+
+        if self.batched_writes {
+            let blockhash = self.blockhash_for_next_transaction()?;
+            let transactions = instructions
+                .map(|instruction| {
+                    instructions_to_tx(&self.payer, blockhash, &[instruction], &[signer])
+                })
+                .collect::<Vec<_>>();
+            self.send_transactions(transactions)?;
+        } else {
+            for instruction in instructions {
+                self.run_instruction(instruction, &[signer])?;
+            }
         }
 
         Ok(())
@@ -488,6 +2317,85 @@ impl<C: ClientSync> Environment<C> {
         Ok(())
     }
 
+    /// Verifies that the program deployed with [Self::deploy_program] contains `data`.
+    ///
+    /// Returns `ClientError::AccountDataMismatch` if the deployed bytes differ from `data`.
+    pub fn verify_program(
+        &mut self,
+        program_id: Pubkey,
+        data: &[u8],
+    ) -> Result<(), ClientErrorSync<C>> {
+        let account = self.client.get_account(program_id)?;
+        if account.data != data {
+            return Err(ClientError::AccountDataMismatch(program_id));
+        }
+        Ok(())
+    }
+
+    /// Verifies that the program deployed with [Self::deploy_upgradeable_program] contains `data`.
+    ///
+    /// Returns `ClientError::AccountDataMismatch` if the deployed bytes differ from `data`.
+    pub fn verify_upgradeable_program(
+        &mut self,
+        program_id: Pubkey,
+        data: &[u8],
+    ) -> Result<(), ClientErrorSync<C>> {
+        let (programdata_address, _) =
+            Pubkey::find_program_address(&[&program_id.to_bytes()], &bpf_loader_upgradeable::ID);
+
+        let account = self.client.get_account(programdata_address)?;
+        let offset = UpgradeableLoaderState::programdata_data_offset().unwrap();
+        // ProgramData account may reserve more space than the actual program
+        if account.data.get(offset..offset + data.len()) != Some(data) {
+            return Err(ClientError::AccountDataMismatch(programdata_address));
+        }
+        Ok(())
+    }
+
+    /// Gets the upgrade authority of an upgradeable program.
+    /// `None` means that the program is immutable.
+    ///
+    /// Returns `ClientError::InvalidAccountData` if the program is not an upgradeable program.
+    pub fn program_upgrade_authority(
+        &mut self,
+        program_id: Pubkey,
+    ) -> Result<Option<Pubkey>, ClientErrorSync<C>> {
+        Ok(self.programdata_state(program_id)?.1)
+    }
+
+    /// Gets the slot at which an upgradeable program was last deployed or upgraded.
+    ///
+    /// Returns `ClientError::InvalidAccountData` if the program is not an upgradeable program.
+    pub fn program_deployed_slot(
+        &mut self,
+        program_id: Pubkey,
+    ) -> Result<Slot, ClientErrorSync<C>> {
+        Ok(self.programdata_state(program_id)?.0)
+    }
+
+    /// Reads the deployed slot and the upgrade authority from the ProgramData account of a program.
+    fn programdata_state(
+        &mut self,
+        program_id: Pubkey,
+    ) -> Result<(Slot, Option<Pubkey>), ClientErrorSync<C>> {
+        let program = self.client.get_account(program_id)?;
+        let programdata_address = match program.state() {
+            Ok(UpgradeableLoaderState::Program {
+                programdata_address,
+            }) if program.owner == bpf_loader_upgradeable::ID => programdata_address,
+            _ => return Err(ClientError::InvalidAccountData(program_id)),
+        };
+
+        let programdata = self.client.get_account(programdata_address)?;
+        match programdata.state() {
+            Ok(UpgradeableLoaderState::ProgramData {
+                slot,
+                upgrade_authority_address,
+            }) => Ok((slot, upgrade_authority_address)),
+            _ => Err(ClientError::InvalidAccountData(programdata_address)),
+        }
+    }
+
     /// Deploys an upgradeable program and returns its ProgramData address
     pub fn deploy_upgradeable_program(
         &mut self,
@@ -519,19 +2427,14 @@ impl<C: ClientSync> Environment<C> {
             &[buffer_account],
         )?;
 
-        let mut offset = 0usize;
-        for chunk in data.chunks(900) {
-            self.run_instruction(
-                bpf_loader_upgradeable::write(
-                    &buffer_account.pubkey(),
-                    &authority_account.pubkey(),
-                    offset as u32,
-                    chunk.to_vec(),
-                ),
-                &[authority_account],
-            )?;
-            offset += chunk.len();
-        }
+        self.write_chunks(data, authority_account, |offset, chunk| {
+            bpf_loader_upgradeable::write(
+                &buffer_account.pubkey(),
+                &authority_account.pubkey(),
+                offset,
+                chunk,
+            )
+        })?;
 
         self.run_instructions(
             &bpf_loader_upgradeable::deploy_with_max_program_len(
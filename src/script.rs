@@ -0,0 +1,82 @@
+//! Replayable script of the transactions sent by an environment.
+//!
+//! Each line of a script is a JSON object that contains a transaction
+//! in the wire format encoded with base64, and the labels of its signers.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, transaction::Transaction};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ScriptEntry {
+    /// Base64 encoding of the serialized transaction
+    pub transaction: String,
+    /// Labels of the signers, or their addresses if they are not labeled
+    pub signers: Vec<String>,
+}
+
+impl ScriptEntry {
+    pub fn new(transaction: &Transaction, address_labels: &HashMap<Pubkey, String>) -> Self {
+        let bytes = bincode::serialize(transaction).expect("Transaction should be serializable");
+
+        let num_required_signatures = transaction.message.header.num_required_signatures as usize;
+        let signers = transaction
+            .message
+            .account_keys
+            .iter()
+            .take(num_required_signatures)
+            .map(|address| match address_labels.get(address) {
+                Some(label) => label.clone(),
+                None => address.to_string(),
+            })
+            .collect();
+
+        ScriptEntry {
+            transaction: base64::encode(bytes),
+            signers,
+        }
+    }
+
+    pub fn into_transaction(self) -> Option<Transaction> {
+        let bytes = base64::decode(self.transaction).ok()?;
+        bincode::deserialize(&bytes).ok()
+    }
+}
+
+/// Appends the transaction to the script as a new line.
+pub(crate) fn write_entry(
+    file: &mut fs::File,
+    transaction: &Transaction,
+    address_labels: &HashMap<Pubkey, String>,
+) -> io::Result<()> {
+    serde_json::to_writer(&mut *file, &ScriptEntry::new(transaction, address_labels))?;
+    writeln!(file)
+}
+
+/// Reads the transactions of the script in order.
+pub(crate) fn read_transactions(path: impl AsRef<Path>) -> io::Result<Vec<Transaction>> {
+    let file = io::BufReader::new(fs::File::open(path)?);
+    file.lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|line| {
+            let entry: ScriptEntry = serde_json::from_str(&line?)?;
+            entry.into_transaction().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid transaction in the script",
+                )
+            })
+        })
+        .collect()
+}
+
+/// Creates the script file if a path is given.
+pub(crate) fn create_file(path: Option<&Path>) -> Option<fs::File> {
+    path.map(|path| fs::File::create(path).expect("Failed to create the transaction script"))
+}
@@ -0,0 +1,69 @@
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::{Signer, SignerError},
+    transaction::Transaction,
+};
+
+/// A transaction that is signed incrementally by multiple parties before submission.
+///
+/// This models co-signing flows where each signer signs the same message at a different time.
+/// Create one with [Environment::partial_transaction](crate::Environment::partial_transaction)
+/// and submit it with
+/// [Environment::send_partial_transaction](crate::Environment::send_partial_transaction).
+#[derive(Clone, Debug)]
+pub struct PartialTransaction {
+    transaction: Transaction,
+}
+
+impl PartialTransaction {
+    pub(crate) fn new(
+        payer: &Keypair,
+        recent_blockhash: Hash,
+        instructions: &[Instruction],
+    ) -> Self {
+        let message = Message::new(instructions, Some(&payer.pubkey()));
+        let mut transaction = Transaction::new_unsigned(message);
+        transaction.partial_sign(&[payer], recent_blockhash);
+        PartialTransaction { transaction }
+    }
+
+    /// Adds a signature of `keypair`, which should be one of the required signers.
+    pub fn sign_with(&mut self, keypair: &Keypair) -> Result<&mut Self, SignerError> {
+        let recent_blockhash = self.transaction.message.recent_blockhash;
+        self.transaction
+            .try_partial_sign(&[keypair], recent_blockhash)?;
+        Ok(self)
+    }
+
+    /// Returns whether every required signer has signed the transaction.
+    pub fn is_fully_signed(&self) -> bool {
+        self.transaction.is_signed()
+    }
+
+    /// Returns the required signers who have not signed the transaction yet.
+    pub fn missing_signers(&self) -> Vec<Pubkey> {
+        let num_required_signatures = self.transaction.message.header.num_required_signatures;
+        self.transaction
+            .message
+            .account_keys
+            .iter()
+            .zip(self.transaction.signatures.iter())
+            .take(num_required_signatures as usize)
+            .filter(|(_, signature)| **signature == Default::default())
+            .map(|(pubkey, _)| *pubkey)
+            .collect()
+    }
+
+    /// Returns the underlying transaction.
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub(crate) fn into_transaction(self) -> Transaction {
+        self.transaction
+    }
+}
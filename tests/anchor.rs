@@ -0,0 +1,81 @@
+#![cfg(feature = "anchor")]
+
+use std::convert::Infallible;
+
+use solana_sdk::{instruction::InstructionError, pubkey::Pubkey, transaction::TransactionError};
+use sunburn::client::{ClientError, DynClientError, TransactionDetails};
+
+#[derive(Debug, PartialEq)]
+enum ErrorCode {
+    Unauthorized,
+    InvalidAmount,
+}
+
+impl TryFrom<u32> for ErrorCode {
+    type Error = ();
+
+    fn try_from(index: u32) -> Result<Self, Self::Error> {
+        match index {
+            0 => Ok(ErrorCode::Unauthorized),
+            1 => Ok(ErrorCode::InvalidAmount),
+            _ => Err(()),
+        }
+    }
+}
+
+fn failed_with(error: TransactionError) -> ClientError<Infallible> {
+    ClientError::FailedTransaction {
+        error,
+        details: TransactionDetails {
+            log_messages: Vec::new(),
+            units_consumed: None,
+            inner_instructions: Vec::new(),
+            rent_collections: Vec::new(),
+        },
+    }
+}
+
+#[test]
+fn anchor_error_decodes_custom_error_codes() {
+    let error = failed_with(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(6001),
+    ));
+    assert_eq!(
+        error.anchor_error::<ErrorCode>(),
+        Some(ErrorCode::InvalidAmount)
+    );
+    assert_eq!(
+        DynClientError::from(error).anchor_error::<ErrorCode>(),
+        Some(ErrorCode::InvalidAmount)
+    );
+}
+
+#[test]
+fn anchor_error_ignores_other_errors() {
+    // Below the offset of Anchor error codes
+    let error = failed_with(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(42),
+    ));
+    assert_eq!(error.anchor_error::<ErrorCode>(), None);
+
+    // Not a variant of the error enum
+    let error = failed_with(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(6002),
+    ));
+    assert_eq!(error.anchor_error::<ErrorCode>(), None);
+
+    let error = failed_with(TransactionError::InstructionError(
+        0,
+        InstructionError::InvalidArgument,
+    ));
+    assert_eq!(error.anchor_error::<ErrorCode>(), None);
+
+    let error = failed_with(TransactionError::AccountNotFound);
+    assert_eq!(error.anchor_error::<ErrorCode>(), None);
+
+    let error: ClientError<Infallible> = ClientError::AccountNotFound(Pubkey::new_unique());
+    assert_eq!(error.anchor_error::<ErrorCode>(), None);
+}
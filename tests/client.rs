@@ -0,0 +1,84 @@
+use std::convert::Infallible;
+
+use solana_sdk::pubkey::Pubkey;
+use spl_token::instruction::TokenInstruction;
+use sunburn::client::{decode_token_instruction, ClientError, TransactionDetails};
+
+fn details(log_messages: &[&str]) -> TransactionDetails {
+    TransactionDetails {
+        log_messages: log_messages.iter().map(|log| log.to_string()).collect(),
+        units_consumed: None,
+        inner_instructions: Vec::new(),
+        rent_collections: Vec::new(),
+    }
+}
+
+#[test]
+fn panic_is_reported_as_abort() {
+    let panicked = details(&[
+        "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr invoke [1]",
+        "Program failed to complete: BPF program panicked",
+        "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr failed: BPF program panicked",
+    ]);
+    assert!(panicked.aborted());
+}
+
+#[test]
+fn error_return_is_not_reported_as_abort() {
+    let failed = details(&[
+        "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr invoke [1]",
+        "Program MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr failed: custom program error: 0x1",
+    ]);
+    assert!(!failed.aborted());
+}
+
+#[test]
+fn cpi_depth_is_read_from_the_logs() {
+    let nested = details(&[
+        "Program ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL invoke [1]",
+        "Program 11111111111111111111111111111111 invoke [2]",
+        "Program 11111111111111111111111111111111 success",
+        "Program ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL success",
+    ]);
+    assert_eq!(nested.max_cpi_depth(), 2);
+    nested.assert_max_cpi_depth(2);
+
+    assert_eq!(details(&[]).max_cpi_depth(), 0);
+}
+
+#[test]
+#[should_panic(expected = "Invocation depth 2 exceeds the maximum of 1")]
+fn cpi_depth_limit_is_asserted() {
+    let nested = details(&[
+        "Program ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL invoke [1]",
+        "Program 11111111111111111111111111111111 invoke [2]",
+    ]);
+    nested.assert_max_cpi_depth(1);
+}
+
+#[test]
+fn transaction_too_large_reports_the_packet_limit() {
+    let error: ClientError<Infallible> = ClientError::TransactionTooLarge { size: 2000 };
+    assert_eq!(
+        error.to_string(),
+        "transaction of 2000 bytes exceeds the packet limit of 1232 bytes"
+    );
+}
+
+#[test]
+fn token_instruction_is_decoded() {
+    let instruction = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        &[],
+        5,
+    )
+    .unwrap();
+    assert_eq!(
+        decode_token_instruction(&instruction.data),
+        Some(TokenInstruction::Transfer { amount: 5 })
+    );
+    assert_eq!(decode_token_instruction(&[255]), None);
+}
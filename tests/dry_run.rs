@@ -0,0 +1,33 @@
+use solana_sdk::{
+    instruction::Instruction, native_token::sol_to_lamports, pubkey::Pubkey, signer::Signer,
+    system_instruction,
+};
+use sunburn::{client::ClientError, EnvironmentGenesis};
+
+const MEMO_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+fn memo(text: &str) -> Instruction {
+    Instruction::new_with_bytes(MEMO_PROGRAM_ID, text.as_bytes(), Vec::new())
+}
+
+#[test]
+fn transactions_are_recorded_without_execution() {
+    let mut env = EnvironmentGenesis::new().build_dry_run_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    env.run_instruction(
+        system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    env.run_instruction(memo("recorded"), &[]).unwrap();
+
+    let transactions = env.recorded_transactions();
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[0].message.account_keys[0], payer);
+    assert!(matches!(
+        env.get_account(recipient),
+        Err(ClientError::AccountNotFound(_))
+    ));
+}
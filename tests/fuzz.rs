@@ -0,0 +1,23 @@
+#![cfg(feature = "fuzz")]
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use sunburn::{fuzz::FuzzEnvironment, EnvironmentGenesis};
+
+const MEMO_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+#[test]
+fn memo_sequences_do_not_abort() {
+    let mut fuzz = FuzzEnvironment::new(0, || EnvironmentGenesis::new().build_local_sync());
+    fuzz.add_generator(|u| {
+        let len = u.int_in_range(0..=32)?;
+        Ok(Instruction::new_with_bytes(
+            MEMO_PROGRAM_ID,
+            u.bytes(len)?,
+            Vec::new(),
+        ))
+    });
+
+    // Invalid UTF-8 is cleanly rejected by the memo program
+    let findings = fuzz.run(8).unwrap();
+    assert!(findings.is_empty());
+}
@@ -0,0 +1,10 @@
+use solana_sdk::pubkey::Pubkey;
+use sunburn::ix::instruction_with_bincode;
+
+#[test]
+fn instruction_data_is_serialized_with_bincode() {
+    let program_id = Pubkey::new_unique();
+    let instruction = instruction_with_bincode(program_id, Vec::new(), &(1u8, 42u64));
+    assert_eq!(instruction.program_id, program_id);
+    assert_eq!(instruction.data, [1, 42, 0, 0, 0, 0, 0, 0, 0]);
+}
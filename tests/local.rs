@@ -0,0 +1,2045 @@
+use std::{cell::RefCell, convert::Infallible, env::temp_dir, fs, rc::Rc};
+
+use solana_program_test::processor;
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    account::AccountSharedData,
+    account_info::AccountInfo,
+    account_utils::StateMut,
+    bpf_loader_upgradeable,
+    clock::MAX_PROCESSING_AGE,
+    entrypoint::ProgramResult,
+    feature, feature_set,
+    genesis_config::GenesisConfig,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    native_loader,
+    native_token::sol_to_lamports,
+    packet::PACKET_DATA_SIZE,
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    stake::{self, state::StakeState},
+    system_instruction::{self, MAX_PERMITTED_DATA_LENGTH},
+    system_program,
+    sysvar::{
+        self,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
+    transaction::{Transaction, TransactionError},
+};
+use solana_vote_program::vote_state::VoteState;
+use spl_associated_token_account::get_associated_token_address;
+use sunburn::{
+    client::{ClientError, ClientSync, LocalClientSync, TransactionDetails},
+    get_associated_token_address_with_program_id, AccountConfig, Environment, EnvironmentGenesis,
+    Loader, LogConfig,
+};
+
+const MEMO_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+fn memo(text: &str) -> Instruction {
+    Instruction::new_with_bytes(MEMO_PROGRAM_ID, text.as_bytes(), Vec::new())
+}
+
+fn memo_program_data() -> Vec<u8> {
+    EnvironmentGenesis::new().accounts()[&MEMO_PROGRAM_ID]
+        .data
+        .clone()
+}
+
+fn create_mint(env: &mut Environment<LocalClientSync>, authority: &Keypair) -> Pubkey {
+    let mint = Keypair::new();
+    env.create_token_mint(&mint, authority.pubkey(), None, 0)
+        .unwrap();
+    mint.pubkey()
+}
+
+// We need this deprecated version of API for our target version of Solana
+#[allow(deprecated)]
+fn create_associated_token_account(payer: Pubkey, owner: Pubkey, mint: Pubkey) -> Instruction {
+    spl_associated_token_account::create_associated_token_account(&payer, &owner, &mint)
+}
+
+/// Sends the instructions directly to the client to inspect the transaction details.
+fn execute(
+    env: &mut Environment<LocalClientSync>,
+    instructions: &[Instruction],
+) -> Result<TransactionDetails, ClientError<Infallible>> {
+    let blockhash = env.client().latest_blockhash().unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&env.payer().pubkey()),
+        &[env.payer()],
+        blockhash,
+    );
+    env.client().send_transaction(transaction)
+}
+
+fn noop(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+fn fail_with_seven(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Err(ProgramError::Custom(7))
+}
+
+fn bank_with_payer(payer: &Keypair) -> Bank {
+    let genesis_config = GenesisConfig::new(
+        &[(
+            payer.pubkey(),
+            AccountSharedData::new(sol_to_lamports(10.0), 0, &system_program::ID),
+        )],
+        &[],
+    );
+    Bank::new_for_tests(&genesis_config)
+}
+
+#[test]
+fn multisig_requires_m_signers() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let signers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let signer_pubkeys: Vec<_> = signers.iter().map(|signer| signer.pubkey()).collect();
+
+    let multisig = Keypair::new();
+    env.create_multisig(&multisig, &signer_pubkeys, 2).unwrap();
+
+    let mint = Keypair::new();
+    env.create_token_mint(&mint, multisig.pubkey(), None, 0)
+        .unwrap();
+    let source = env
+        .create_associated_token_account(multisig.pubkey(), mint.pubkey())
+        .unwrap();
+    let destination = env
+        .create_associated_token_account(Pubkey::new_unique(), mint.pubkey())
+        .unwrap();
+
+    env.mint_tokens_multisig(
+        mint.pubkey(),
+        multisig.pubkey(),
+        &[&signers[0], &signers[2]],
+        source,
+        100,
+    )
+    .unwrap();
+    env.transfer_tokens_multisig(
+        source,
+        multisig.pubkey(),
+        &[&signers[1], &signers[2]],
+        destination,
+        40,
+    )
+    .unwrap();
+    assert_eq!(env.token_balance(source).unwrap(), 60);
+    assert_eq!(env.token_balance(destination).unwrap(), 40);
+
+    // A single signer does not meet the threshold of two
+    let result =
+        env.transfer_tokens_multisig(source, multisig.pubkey(), &[&signers[0]], destination, 10);
+    assert!(matches!(result, Err(ClientError::FailedTransaction { .. })));
+    assert_eq!(env.token_balance(source).unwrap(), 60);
+}
+
+#[test]
+fn get_or_create_associated_token_account_is_idempotent() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let owner = Pubkey::new_unique();
+
+    let address = env
+        .get_or_create_associated_token_account(owner, mint)
+        .unwrap();
+    assert_eq!(address, get_associated_token_address(&owner, &mint));
+    assert_eq!(
+        env.get_or_create_associated_token_account(owner, mint)
+            .unwrap(),
+        address
+    );
+    assert_eq!(env.token_balance(address).unwrap(), 0);
+}
+
+#[test]
+fn associated_token_account_with_spl_token_program_id() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let owner = Pubkey::new_unique();
+
+    let address = env
+        .create_associated_token_account_with_program_id(owner, mint, spl_token::ID)
+        .unwrap();
+    assert_eq!(address, get_associated_token_address(&owner, &mint));
+    assert_eq!(
+        address,
+        get_associated_token_address_with_program_id(&owner, &mint, &spl_token::ID)
+    );
+    assert_eq!(env.token_balance(address).unwrap(), 0);
+}
+
+#[test]
+fn fees_are_disabled_by_default() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let before = env.get_account(payer).unwrap().lamports;
+    env.run_instruction(
+        system_instruction::transfer(&payer, &Pubkey::new_unique(), sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    let after = env.get_account(payer).unwrap().lamports;
+    assert_eq!(before - after, sol_to_lamports(1.0));
+}
+
+#[test]
+fn fees_are_charged_when_enabled() {
+    let mut env = EnvironmentGenesis::new().with_fees(true).build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let before = env.get_account(payer).unwrap().lamports;
+    env.run_instruction(
+        system_instruction::transfer(&payer, &Pubkey::new_unique(), sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    let after = env.get_account(payer).unwrap().lamports;
+    // The payer is the only signer
+    assert_eq!(before - after, sol_to_lamports(1.0) + 10_000);
+}
+
+#[test]
+fn features_are_activated_at_genesis() {
+    let activated = feature_set::vote_stake_checked_instructions::id();
+    let mut env = EnvironmentGenesis::new()
+        .activate_feature(activated)
+        .build_local_sync();
+
+    let account = env.get_account(activated).unwrap();
+    let feature = feature::from_account(&account).unwrap();
+    assert_eq!(feature.activated_at, Some(0));
+}
+
+#[test]
+fn deactivated_feature_is_not_created() {
+    let deactivated = feature_set::vote_stake_checked_instructions::id();
+    let mut env = EnvironmentGenesis::new()
+        .activate_all_features()
+        .deactivate_feature(deactivated)
+        .build_local_sync();
+
+    let activated = feature_set::rent_for_sysvars::id();
+    assert!(env.get_account(activated).is_ok());
+    assert!(matches!(
+        env.get_account(deactivated),
+        Err(ClientError::AccountNotFound(address)) if address == deactivated
+    ));
+}
+
+#[test]
+fn keypairs_from_seeds_are_deterministic() {
+    let first = EnvironmentGenesis::keypair_from_seed(b"alice");
+    let second = EnvironmentGenesis::keypair_from_seed(b"alice");
+    assert_eq!(first.pubkey(), second.pubkey());
+    assert_ne!(
+        first.pubkey(),
+        EnvironmentGenesis::keypair_from_seed(b"bob").pubkey()
+    );
+
+    let mut env = EnvironmentGenesis::new()
+        .add_account_from_seed(
+            "Alice",
+            b"alice",
+            AccountConfig {
+                lamports: Some(sol_to_lamports(1.0)),
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+    assert_eq!(
+        env.get_account(first.pubkey()).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn raw_transaction_is_sent_without_extra_signatures() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let cosigner = Pubkey::new_unique();
+    let blockhash = env.wait_for_tick().unwrap();
+
+    // The memo program requires every account to be a signer
+    let instruction = Instruction::new_with_bytes(
+        MEMO_PROGRAM_ID,
+        b"under-signed",
+        vec![AccountMeta::new_readonly(cosigner, false)],
+    );
+    let transaction =
+        Transaction::new_signed_with_payer(&[instruction], Some(&payer), &[env.payer()], blockhash);
+
+    let result = env.send_raw_transaction(transaction);
+    assert!(matches!(
+        result,
+        Err(ClientError::FailedTransaction {
+            error: TransactionError::InstructionError(
+                0,
+                InstructionError::MissingRequiredSignature
+            ),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn last_used_blockhash_is_recorded() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    assert_eq!(env.last_used_blockhash(), None);
+
+    env.run_instruction(memo("blockhash"), &[]).unwrap();
+    let latest = env.client().latest_blockhash().unwrap();
+    assert_eq!(env.last_used_blockhash(), Some(latest));
+}
+
+#[test]
+fn account_is_created_with_owner() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let account = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    env.create_account(&account, owner, 100).unwrap();
+    let created = env.get_account(account.pubkey()).unwrap();
+    assert_eq!(created.owner, owner);
+    assert_eq!(created.data, vec![0; 100]);
+    assert_eq!(created.lamports, env.rent_exemption_amount(100));
+}
+
+#[test]
+fn accounts_are_loaded_from_json_dump() {
+    let address = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let path = temp_dir().join(format!("sunburn-json-{}.json", address));
+    let entry = serde_json::json!({
+        "pubkey": address.to_string(),
+        "account": {
+            "lamports": 1_000_000_000u64,
+            "data": ["AAECAw==", "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+        },
+    });
+    fs::write(&path, entry.to_string()).unwrap();
+
+    let mut env = EnvironmentGenesis::new()
+        .add_accounts_from_json(&path)
+        .build_local_sync();
+    fs::remove_file(&path).unwrap();
+
+    let account = env.get_account(address).unwrap();
+    assert_eq!(account.lamports, 1_000_000_000);
+    assert_eq!(account.data, vec![0, 1, 2, 3]);
+    assert_eq!(account.owner, owner);
+    assert!(!account.executable);
+}
+
+#[test]
+fn accounts_are_dumped_in_cli_json_format() {
+    let address = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            address,
+            AccountConfig {
+                lamports: Some(sol_to_lamports(1.0)),
+                data: vec![0, 1, 2, 3],
+                owner,
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    // Accounts that do not exist are skipped
+    let dump = env.client().dump_accounts(&[address, Pubkey::new_unique()]);
+    let entries = dump.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["pubkey"], address.to_string());
+    assert_eq!(entries[0]["account"]["lamports"], sol_to_lamports(1.0));
+    assert_eq!(
+        entries[0]["account"]["data"],
+        serde_json::json!(["AAECAw==", "base64"])
+    );
+    assert_eq!(entries[0]["account"]["owner"], owner.to_string());
+    assert_eq!(entries[0]["account"]["executable"], false);
+}
+
+#[test]
+fn identical_transactions_are_sent_with_auto_advance() {
+    let mut env = EnvironmentGenesis::new()
+        .with_auto_advance_blockhash(true)
+        .build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+    let instruction = system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0));
+
+    env.run_instruction(instruction.clone(), &[]).unwrap();
+    env.run_instruction(instruction, &[]).unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(2.0)
+    );
+}
+
+#[test]
+fn new_blockhash_differs_from_the_previous_one() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let first = env.new_blockhash().unwrap();
+    let second = env.new_blockhash().unwrap();
+    assert_ne!(first, second);
+}
+
+#[test]
+fn clock_is_read_from_the_sysvar() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    assert_eq!(env.slot().unwrap(), 0);
+    assert!(env.unix_timestamp().unwrap() > 0);
+}
+
+#[test]
+fn compute_units_are_reported() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let units = env.run_instructions_units(&[memo("units")], &[]).unwrap();
+    assert!(units > 0);
+
+    env.run_and_assert_units(&[memo("limit")], &[], units * 2)
+        .unwrap();
+}
+
+#[test]
+#[should_panic(expected = "exceeds the limit")]
+fn compute_unit_limit_is_asserted() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    env.run_and_assert_units(&[memo("limit")], &[], 1).unwrap();
+}
+
+#[test]
+fn token_account_is_created_at_keypair_address() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let authority = Keypair::new();
+    let mint = create_mint(&mut env, &authority);
+    let account = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    env.create_token_account(&account, mint, owner).unwrap();
+    env.mint_tokens(mint, &authority, account.pubkey(), 5)
+        .unwrap();
+    assert_eq!(env.token_balance(account.pubkey()).unwrap(), 5);
+
+    let state = spl_token::state::Account::unpack(&env.get_account(account.pubkey()).unwrap().data)
+        .unwrap();
+    assert_eq!(state.mint, mint);
+    assert_eq!(state.owner, owner);
+}
+
+#[test]
+fn instructions_sysvar_is_reconstructed() {
+    let env = EnvironmentGenesis::new().build_local_sync();
+    let instructions = [memo("first"), memo("second")];
+    let mut data = env.instructions_sysvar_data(&instructions, 1);
+
+    let address = sysvar::instructions::id();
+    let owner = sysvar::id();
+    let mut lamports = 0;
+    let account_info = AccountInfo::new(
+        &address,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &owner,
+        false,
+        0,
+    );
+
+    assert_eq!(load_current_index_checked(&account_info).unwrap(), 1);
+    let instruction = load_instruction_at_checked(1, &account_info).unwrap();
+    assert_eq!(instruction.program_id, MEMO_PROGRAM_ID);
+    assert_eq!(instruction.data, b"second");
+}
+
+#[test]
+fn memo_of_invalid_utf8_fails_without_abort() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let instruction = Instruction::new_with_bytes(MEMO_PROGRAM_ID, &[0xff], Vec::new());
+
+    match env.run_instruction(instruction, &[]) {
+        Err(ClientError::FailedTransaction { error, details }) => {
+            assert_eq!(
+                error,
+                TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)
+            );
+            assert!(!details.aborted());
+        }
+        _ => panic!("Memo of invalid UTF-8 should fail"),
+    }
+}
+
+#[test]
+fn oversized_account_data_is_rejected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let data = vec![0; MAX_PERMITTED_DATA_LENGTH as usize + 1];
+
+    let result = env.create_account_with_data(&Keypair::new(), &data);
+    assert!(matches!(
+        result,
+        Err(ClientError::AccountDataTooLarge { size, max })
+            if size == data.len() && max == MAX_PERMITTED_DATA_LENGTH as usize
+    ));
+}
+
+#[test]
+fn program_is_deployed_with_and_without_batched_writes() {
+    let data = memo_program_data();
+    for batched in [false, true] {
+        let mut env = EnvironmentGenesis::new()
+            .with_batched_writes(batched)
+            .build_local_sync();
+        let program = Keypair::new();
+
+        env.deploy_program(&program, &data).unwrap();
+        assert_eq!(env.get_account(program.pubkey()).unwrap().data, data);
+        env.run_instruction(
+            Instruction::new_with_bytes(program.pubkey(), b"deployed", Vec::new()),
+            &[],
+        )
+        .unwrap();
+    }
+}
+
+#[test]
+fn deployed_programs_are_verified() {
+    let data = memo_program_data();
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    env.verify_program(MEMO_PROGRAM_ID, &data).unwrap();
+
+    let mut tampered = data.clone();
+    tampered[0] ^= 1;
+    assert!(matches!(
+        env.verify_program(MEMO_PROGRAM_ID, &tampered),
+        Err(ClientError::AccountDataMismatch(address)) if address == MEMO_PROGRAM_ID
+    ));
+
+    let program = Keypair::new();
+    env.deploy_upgradeable_program(&program, &Keypair::new(), &Keypair::new(), &data, true)
+        .unwrap();
+    env.verify_upgradeable_program(program.pubkey(), &data)
+        .unwrap();
+    assert!(matches!(
+        env.verify_upgradeable_program(program.pubkey(), &tampered),
+        Err(ClientError::AccountDataMismatch(_))
+    ));
+}
+
+#[test]
+fn token_accounts_are_found_by_owner() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let first_mint = create_mint(&mut env, &Keypair::new());
+    let second_mint = create_mint(&mut env, &Keypair::new());
+    let owner = Pubkey::new_unique();
+
+    let first = env
+        .create_associated_token_account(owner, first_mint)
+        .unwrap();
+    env.create_associated_token_account(owner, second_mint)
+        .unwrap();
+    env.create_associated_token_account(Pubkey::new_unique(), first_mint)
+        .unwrap();
+
+    assert_eq!(
+        env.get_token_accounts_by_owner(owner, None).unwrap().len(),
+        2
+    );
+    let accounts = env
+        .get_token_accounts_by_owner(owner, Some(first_mint))
+        .unwrap();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].0, first);
+    assert_eq!(accounts[0].1.mint, first_mint);
+}
+
+#[test]
+fn partial_transaction_is_signed_incrementally() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let cosigner = Keypair::new();
+    let instruction = Instruction::new_with_bytes(
+        MEMO_PROGRAM_ID,
+        b"cosigned",
+        vec![AccountMeta::new_readonly(cosigner.pubkey(), true)],
+    );
+
+    let mut transaction = env.partial_transaction(&[instruction]).unwrap();
+    assert!(!transaction.is_fully_signed());
+    assert_eq!(transaction.missing_signers(), vec![cosigner.pubkey()]);
+    assert!(matches!(
+        env.send_partial_transaction(transaction.clone()),
+        Err(ClientError::InvalidTransaction(
+            TransactionError::SignatureFailure
+        ))
+    ));
+
+    transaction.sign_with(&cosigner).unwrap();
+    assert!(transaction.is_fully_signed());
+    env.send_partial_transaction(transaction).unwrap();
+}
+
+#[test]
+fn rent_exemption_is_checked() {
+    let underfunded = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            underfunded,
+            AccountConfig {
+                lamports: Some(1),
+                data: vec![0; 10],
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    assert!(!env.is_rent_exempt(underfunded).unwrap());
+    assert!(matches!(
+        env.assert_rent_exempt(underfunded),
+        Err(ClientError::NotRentExempt(address)) if address == underfunded
+    ));
+
+    let payer = env.payer().pubkey();
+    env.assert_rent_exempt(payer).unwrap();
+}
+
+#[test]
+fn expired_blockhash_is_rejected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let pinned = env.client().latest_blockhash().unwrap();
+    env.run_instructions_with_blockhash(&[memo("fresh")], &[], pinned)
+        .unwrap();
+
+    for _ in 0..=MAX_PROCESSING_AGE {
+        env.new_blockhash().unwrap();
+    }
+    let result = env.run_instructions_with_blockhash(&[memo("expired")], &[], pinned);
+    assert!(matches!(
+        result,
+        Err(ClientError::InvalidTransaction(
+            TransactionError::BlockhashNotFound
+        ))
+    ));
+}
+
+#[test]
+fn realloc_preserves_total_lamports() {
+    let funder = EnvironmentGenesis::keypair_from_seed(b"funder");
+    let target = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            funder.pubkey(),
+            AccountConfig {
+                lamports: Some(sol_to_lamports(10.0)),
+                ..Default::default()
+            },
+        )
+        .add_account(
+            target,
+            AccountConfig {
+                lamports: Some(Rent::default().minimum_balance(10)),
+                data: vec![1; 10],
+                owner: Pubkey::new_unique(),
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    let total = |env: &mut Environment<LocalClientSync>| {
+        env.get_account(funder.pubkey()).unwrap().lamports
+            + env.get_account(target).unwrap().lamports
+    };
+    let before = total(&mut env);
+
+    env.realloc_account(target, 100, &funder).unwrap();
+    let account = env.get_account(target).unwrap();
+    assert_eq!(account.data.len(), 100);
+    assert_eq!(&account.data[..10], &[1; 10]);
+    assert!(env.is_rent_exempt(target).unwrap());
+    assert_eq!(total(&mut env), before);
+
+    env.realloc_account(target, 5, &funder).unwrap();
+    assert_eq!(env.get_account(target).unwrap().data.len(), 5);
+    assert!(env.is_rent_exempt(target).unwrap());
+    assert_eq!(total(&mut env), before);
+}
+
+#[test]
+fn realloc_rejects_oversized_data() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = EnvironmentGenesis::keypair_from_seed(b"realloc payer");
+    let target = Pubkey::new_unique();
+
+    let result = env.realloc_account(target, MAX_PERMITTED_DATA_LENGTH as usize + 1, &payer);
+    assert!(matches!(
+        result,
+        Err(ClientError::AccountDataTooLarge { max, .. }) if max == MAX_PERMITTED_DATA_LENGTH as usize
+    ));
+}
+
+#[test]
+fn uninitialized_token_account_is_rejected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let account = Keypair::new();
+    env.create_account(&account, spl_token::ID, spl_token::state::Account::LEN)
+        .unwrap();
+
+    assert!(matches!(
+        env.token_account(account.pubkey()),
+        Err(ClientError::InvalidAccountData(address)) if address == account.pubkey()
+    ));
+    assert!(matches!(
+        env.token_balance(account.pubkey()),
+        Err(ClientError::InvalidAccountData(_))
+    ));
+}
+
+#[test]
+fn unchecked_token_balance_reads_uninitialized_accounts() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let account = Keypair::new();
+    env.create_account(&account, spl_token::ID, spl_token::state::Account::LEN)
+        .unwrap();
+
+    assert_eq!(env.token_balance_unchecked(account.pubkey()).unwrap(), 0);
+}
+
+#[test]
+fn tokens_are_minted_to_a_new_account() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let authority = Keypair::new();
+    let mint = create_mint(&mut env, &authority);
+    let owner = Pubkey::new_unique();
+
+    let address = env
+        .mint_to_new_account(mint, &authority, owner, 10)
+        .unwrap();
+    assert_eq!(address, get_associated_token_address(&owner, &mint));
+
+    // The existing account is reused
+    env.mint_to_new_account(mint, &authority, owner, 5).unwrap();
+    assert_eq!(env.token_balance(address).unwrap(), 15);
+}
+
+#[test]
+fn inner_instructions_are_recorded() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let payer = env.payer().pubkey();
+    let owner = Pubkey::new_unique();
+
+    let details = execute(
+        &mut env,
+        &[create_associated_token_account(payer, owner, mint)],
+    )
+    .unwrap();
+
+    let token_instructions: Vec<_> = details.inner_instructions_of(spl_token::ID).collect();
+    assert_eq!(token_instructions.len(), 1);
+    assert_eq!(token_instructions[0].index, 0);
+    assert_eq!(
+        token_instructions[0].accounts[0],
+        get_associated_token_address(&owner, &mint)
+    );
+    assert!(details
+        .inner_instructions_of(system_program::ID)
+        .next()
+        .is_some());
+}
+
+#[test]
+fn vote_and_stake_accounts_are_added() {
+    let vote = Pubkey::new_unique();
+    let node = Pubkey::new_unique();
+    let voter = Pubkey::new_unique();
+    let vote_withdrawer = Pubkey::new_unique();
+    let stake = Pubkey::new_unique();
+    let staker = Pubkey::new_unique();
+    let stake_withdrawer = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_vote_account(vote, node, voter, vote_withdrawer, 10)
+        .add_stake_account(stake, staker, stake_withdrawer, sol_to_lamports(10.0))
+        .build_local_sync();
+
+    let vote_account = env.get_account(vote).unwrap();
+    assert_eq!(vote_account.owner, solana_vote_program::id());
+    let vote_state = VoteState::deserialize(&vote_account.data).unwrap();
+    assert_eq!(vote_state.node_pubkey, node);
+    assert_eq!(vote_state.authorized_withdrawer, vote_withdrawer);
+    assert_eq!(vote_state.commission, 10);
+
+    let stake_account = env.get_account(stake).unwrap();
+    assert_eq!(stake_account.owner, stake::program::ID);
+    let state: StakeState = stake_account.state().unwrap();
+    let meta = state.meta().unwrap();
+    assert_eq!(meta.authorized.staker, staker);
+    assert_eq!(meta.authorized.withdrawer, stake_withdrawer);
+}
+
+#[test]
+fn reset_discards_every_change() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+    let instruction = system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0));
+    env.run_instruction(instruction.clone(), &[]).unwrap();
+
+    env.reset();
+    assert!(matches!(
+        env.get_account(recipient),
+        Err(ClientError::AccountNotFound(_))
+    ));
+    assert_eq!(env.last_used_blockhash(), None);
+
+    // The payer is kept across resets
+    env.run_instruction(instruction, &[]).unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn estimated_fee_matches_the_charged_fee() {
+    let mut env = EnvironmentGenesis::new().with_fees(true).build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let fee = env.estimate_fee(&[memo("paid")], &[]).unwrap();
+    assert_eq!(fee, 10_000);
+
+    let before = env.get_account(payer).unwrap().lamports;
+    env.run_instruction(memo("paid"), &[]).unwrap();
+    assert_eq!(before - env.get_account(payer).unwrap().lamports, fee);
+}
+
+#[test]
+fn estimated_fee_is_zero_without_fees() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    assert_eq!(env.estimate_fee(&[memo("free")], &[]).unwrap(), 0);
+}
+
+#[test]
+fn custom_loader_is_registered() {
+    let program_id = Pubkey::new_unique();
+    let loader: Loader = ("noop".to_string(), program_id, processor!(noop).unwrap());
+    let mut env = EnvironmentGenesis::new()
+        .add_loader(loader)
+        .build_local_sync();
+
+    env.run_instruction(
+        Instruction::new_with_bytes(program_id, &[], Vec::new()),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        env.get_account(program_id).unwrap().owner,
+        native_loader::ID
+    );
+}
+
+#[test]
+#[should_panic(expected = "Loader added to the same address more than once")]
+fn default_loader_cannot_be_added_again() {
+    EnvironmentGenesis::new().add_loader(solana_bpf_loader_program::solana_bpf_loader_program!());
+}
+
+#[test]
+fn upgradeable_program_is_placed_directly() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let program_id = Pubkey::new_unique();
+    let data = memo_program_data();
+
+    let programdata =
+        env.client()
+            .set_upgradeable_program(program_id, Some(Pubkey::new_unique()), &data, 0);
+    assert_eq!(
+        programdata,
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0
+    );
+
+    let program = env.get_account(program_id).unwrap();
+    assert_eq!(program.owner, bpf_loader_upgradeable::ID);
+    assert!(program.executable);
+    env.verify_upgradeable_program(program_id, &data).unwrap();
+}
+
+#[test]
+fn payer_balance_is_read() {
+    let mut env = EnvironmentGenesis::new()
+        .with_payer_balance_warning(sol_to_lamports(1.0))
+        .build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let before = env.payer_balance().unwrap();
+    assert_eq!(before, env.get_account(payer).unwrap().lamports);
+    env.run_instruction(
+        system_instruction::transfer(&payer, &Pubkey::new_unique(), sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(before - env.payer_balance().unwrap(), sol_to_lamports(1.0));
+}
+
+#[test]
+fn failed_instruction_is_correlated() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let instructions = [
+        memo("before"),
+        system_instruction::transfer(&payer, &Pubkey::new_unique(), u64::MAX),
+    ];
+
+    let error = env.run_instructions(&instructions, &[]).unwrap_err();
+    let (instruction, instruction_error) = error.failed_instruction(&instructions).unwrap();
+    assert_eq!(instruction, &instructions[1]);
+    // SystemError::ResultWithNegativeLamports
+    assert_eq!(instruction_error, &InstructionError::Custom(1));
+}
+
+#[test]
+fn mint_is_created_with_supply() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    let address = env.create_mint_with_supply(&mint, 6, owner, 1_000).unwrap();
+    assert_eq!(
+        address,
+        get_associated_token_address(&owner, &mint.pubkey())
+    );
+    assert_eq!(env.token_balance(address).unwrap(), 1_000);
+}
+
+#[test]
+fn environment_is_built_from_an_existing_bank() {
+    let payer = Keypair::new();
+    let bank = bank_with_payer(&payer);
+    let mut env = LocalClientSync::from_bank(bank, payer);
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    env.run_instruction(
+        system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+#[should_panic(expected = "Environment built from an existing bank cannot be reset")]
+fn environment_from_an_existing_bank_cannot_be_reset() {
+    let payer = Keypair::new();
+    let bank = bank_with_payer(&payer);
+    let mut env = LocalClientSync::from_bank(bank, payer);
+    env.reset();
+}
+
+#[test]
+fn program_labels_and_builtins() {
+    let vault = Pubkey::new_unique();
+    let env = EnvironmentGenesis::new()
+        .add_address_label("Vault", vault)
+        .build_local_sync();
+
+    assert_eq!(env.program_label(vault), Some("Vault"));
+    assert_eq!(env.program_label(spl_token::ID), Some("SPL Token"));
+    assert_eq!(env.program_label(Pubkey::new_unique()), None);
+
+    assert!(env.is_builtin(system_program::ID));
+    assert!(env.is_builtin(spl_token::ID));
+    assert!(env.is_builtin(MEMO_PROGRAM_ID));
+    assert!(!env.is_builtin(vault));
+}
+
+#[test]
+fn account_slice_is_read() {
+    let address = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            address,
+            AccountConfig {
+                lamports: Some(sol_to_lamports(1.0)),
+                data: vec![0, 1, 2, 3],
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    assert_eq!(env.get_account_slice(address, 1, 2).unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn supply_matches_the_holders() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let authority = Keypair::new();
+    let mint = create_mint(&mut env, &authority);
+    let first = env
+        .mint_to_new_account(mint, &authority, Pubkey::new_unique(), 30)
+        .unwrap();
+    let second = env
+        .mint_to_new_account(mint, &authority, Pubkey::new_unique(), 12)
+        .unwrap();
+
+    assert_eq!(env.mint_supply(mint).unwrap(), 42);
+    env.assert_supply_matches(mint, &[first, second]).unwrap();
+    assert!(matches!(
+        env.assert_supply_matches(mint, &[first]),
+        Err(ClientError::SupplyMismatch {
+            supply: 42,
+            total: 30,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn verbose_logs_return_the_result_of_the_closure() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    let result = env.with_verbose_logs(|env| {
+        env.run_instruction(
+            system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+            &[],
+        )
+    });
+    assert!(result.is_ok());
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn account_owner_is_checked() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    env.get_account_owned_by(mint, spl_token::ID).unwrap();
+
+    let payer = env.payer().pubkey();
+    assert!(matches!(
+        env.get_account_owned_by(payer, spl_token::ID),
+        Err(ClientError::WrongOwner { address, expected, actual })
+            if address == payer && expected == spl_token::ID && actual == system_program::ID
+    ));
+}
+
+#[test]
+fn token_account_of_another_program_is_rejected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let error = env.token_balance(payer).unwrap_err();
+    assert!(matches!(error, ClientError::WrongOwner { .. }));
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "account {} is owned by {}, expected {}",
+            payer,
+            system_program::ID,
+            spl_token::ID
+        )
+    );
+}
+
+#[test]
+fn memo_is_prepended_to_the_instructions() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    env.run_instructions_with_memo(
+        &[system_instruction::transfer(
+            &payer,
+            &recipient,
+            sol_to_lamports(1.0),
+        )],
+        "payment",
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn ui_amounts_follow_the_mint_decimals() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = Keypair::new();
+    env.create_token_mint(&mint, Pubkey::new_unique(), None, 6)
+        .unwrap();
+
+    assert_eq!(
+        env.ui_amount_to_amount(mint.pubkey(), 1.5).unwrap(),
+        1_500_000
+    );
+    assert_eq!(
+        env.amount_to_ui_amount(mint.pubkey(), 1_500_000).unwrap(),
+        1.5
+    );
+}
+
+#[test]
+fn clock_stays_constant_across_transactions() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let slot = env.slot().unwrap();
+    let timestamp = env.unix_timestamp().unwrap();
+
+    env.run_instruction(memo("clock"), &[]).unwrap();
+    env.new_blockhash().unwrap();
+    assert_eq!(env.slot().unwrap(), slot);
+    assert_eq!(env.unix_timestamp().unwrap(), timestamp);
+}
+
+#[test]
+fn tokens_are_distributed() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let authority = Keypair::new();
+    let mint = create_mint(&mut env, &authority);
+    let owners = [Pubkey::new_unique(), Pubkey::new_unique()];
+
+    let accounts = env
+        .distribute_tokens(mint, &authority, &[(owners[0], 10), (owners[1], 20)])
+        .unwrap();
+    assert_eq!(
+        accounts,
+        vec![
+            get_associated_token_address(&owners[0], &mint),
+            get_associated_token_address(&owners[1], &mint),
+        ]
+    );
+    assert_eq!(env.token_balance(accounts[0]).unwrap(), 10);
+    assert_eq!(env.token_balance(accounts[1]).unwrap(), 20);
+}
+
+#[test]
+fn closed_token_account_rent_goes_to_destination() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let owner = Keypair::new();
+    let account = env
+        .create_associated_token_account(owner.pubkey(), mint)
+        .unwrap();
+    let destination = Pubkey::new_unique();
+
+    env.close_token_account_expect_rent_to(account, destination, &owner)
+        .unwrap();
+    assert_eq!(
+        env.get_account(destination).unwrap().lamports,
+        env.rent_exemption_amount(spl_token::state::Account::LEN)
+    );
+}
+
+#[test]
+fn account_writes_are_reported() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let hook_writes = writes.clone();
+    env.on_account_write(move |address, before, after| {
+        hook_writes.borrow_mut().push((
+            address,
+            before.map(|account| account.lamports),
+            after.map(|account| account.lamports),
+        ));
+    });
+    env.run_instruction(
+        system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+
+    let writes = writes.borrow();
+    assert!(writes.contains(&(recipient, None, Some(sol_to_lamports(1.0)))));
+    assert!(writes.iter().any(|(address, _, _)| *address == payer));
+    assert!(writes
+        .iter()
+        .all(|(address, _, _)| *address != system_program::ID));
+}
+
+#[test]
+fn rent_epoch_is_configured() {
+    let address = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            address,
+            AccountConfig {
+                lamports: Some(sol_to_lamports(1.0)),
+                rent_epoch: Some(7),
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    assert_eq!(env.get_account(address).unwrap().rent_epoch, 7);
+}
+
+#[test]
+fn associated_token_accounts_are_created_together() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let owners: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let pairs: Vec<_> = owners.iter().map(|owner| (*owner, mint)).collect();
+
+    let accounts = env.create_associated_token_accounts(&pairs).unwrap();
+    assert_eq!(accounts.len(), owners.len());
+    for (owner, account) in owners.iter().zip(accounts) {
+        assert_eq!(account, get_associated_token_address(owner, &mint));
+        assert_eq!(env.token_balance(account).unwrap(), 0);
+    }
+}
+
+#[test]
+fn associated_token_address_matches_spl() {
+    let env = EnvironmentGenesis::new().build_local_sync();
+    let owner = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let expected = get_associated_token_address(&owner, &mint);
+    assert_eq!(env.associated_token_address(owner, mint), expected);
+    assert_eq!(
+        env.associated_token_address_with_program_id(owner, mint, spl_token::ID),
+        expected
+    );
+}
+
+#[test]
+fn sent_transaction_is_confirmed() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let blockhash = env.client().latest_blockhash().unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[memo("confirmed")],
+        Some(&payer),
+        &[env.payer()],
+        blockhash,
+    );
+    let signature = transaction.signatures[0];
+
+    env.send_raw_transaction(transaction).unwrap();
+    env.confirm_transaction(signature).unwrap();
+    assert!(matches!(
+        env.confirm_transaction(Signature::default()),
+        Err(ClientError::InvalidTransaction(
+            TransactionError::SignatureFailure
+        ))
+    ));
+}
+
+#[test]
+fn accounts_without_lamports_are_rent_exempt() {
+    let owned = Pubkey::new_unique();
+    let unowned = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            owned,
+            AccountConfig {
+                data: vec![0; 100],
+                ..AccountConfig::rent_exempt(Pubkey::new_unique())
+            },
+        )
+        .add_account(
+            unowned,
+            AccountConfig {
+                data: vec![0; 100],
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    assert!(env.is_rent_exempt(owned).unwrap());
+    assert!(env.is_rent_exempt(unowned).unwrap());
+}
+
+#[test]
+fn genesis_exposes_labels_and_accounts() {
+    let vault = Pubkey::new_unique();
+    let genesis = EnvironmentGenesis::new().add_address_label("Vault", vault);
+
+    assert_eq!(genesis.labels()[&vault], "Vault");
+    assert_eq!(genesis.labels()[&spl_token::ID], "SPL Token");
+    assert!(genesis.accounts().contains_key(&spl_token::ID));
+}
+
+#[test]
+fn recent_logs_keep_the_latest_transactions() {
+    let mut env = EnvironmentGenesis::new()
+        .with_recent_logs_capacity(2)
+        .build_local_sync();
+    for text in ["first", "second", "third"] {
+        env.run_instructions_with_memo(&[], text, &[]).unwrap();
+    }
+
+    let logs: Vec<_> = env.recent_logs().collect();
+    assert_eq!(logs.len(), 2);
+    assert!(logs[0]
+        .log_messages
+        .iter()
+        .any(|log| log.contains("second")));
+    assert!(logs[1].log_messages.iter().any(|log| log.contains("third")));
+}
+
+#[test]
+fn recent_logs_include_failed_transactions() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let instruction = Instruction::new_with_bytes(MEMO_PROGRAM_ID, &[0xff], Vec::new());
+    assert!(env.run_instruction(instruction, &[]).is_err());
+
+    let details = env.recent_logs().last().unwrap();
+    assert!(details
+        .log_messages
+        .iter()
+        .any(|log| log.contains("Invalid UTF-8")));
+}
+
+#[test]
+fn associated_token_account_creation_invokes_at_depth_two() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let payer = env.payer().pubkey();
+
+    let details = execute(
+        &mut env,
+        &[create_associated_token_account(
+            payer,
+            Pubkey::new_unique(),
+            mint,
+        )],
+    )
+    .unwrap();
+    assert_eq!(details.max_cpi_depth(), 2);
+}
+
+#[test]
+fn duplicated_writable_accounts_are_detected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+
+    let duplicated = Instruction::new_with_bytes(
+        MEMO_PROGRAM_ID,
+        b"duplicated",
+        vec![AccountMeta::new(payer, true), AccountMeta::new(payer, true)],
+    );
+    assert_eq!(
+        env.run_instruction_detect_dup(duplicated, &[]).unwrap(),
+        vec![payer]
+    );
+
+    let readonly = Instruction::new_with_bytes(
+        MEMO_PROGRAM_ID,
+        b"readonly",
+        vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(payer, true),
+        ],
+    );
+    assert!(env
+        .run_instruction_detect_dup(readonly, &[])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn environment_starts_at_the_initial_slot() {
+    let mut env = EnvironmentGenesis::new()
+        .with_initial_slot(100)
+        .build_local_sync();
+
+    assert_eq!(env.slot().unwrap(), 100);
+    assert!(env.slot_hashes().unwrap().get(&0).is_some());
+    env.run_instruction(memo("initial slot"), &[]).unwrap();
+}
+
+#[test]
+fn token_balance_is_set_directly() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let owner = Pubkey::new_unique();
+    let account = get_associated_token_address(&owner, &mint);
+
+    env.set_token_balance(account, mint, owner, 50).unwrap();
+    assert_eq!(env.token_balance(account).unwrap(), 50);
+    assert_eq!(env.mint_supply(mint).unwrap(), 50);
+
+    env.set_token_balance(account, mint, owner, 20).unwrap();
+    assert_eq!(env.token_balance(account).unwrap(), 20);
+    assert_eq!(env.mint_supply(mint).unwrap(), 20);
+}
+
+#[test]
+fn minimum_balance_for_rent_exemption_matches_the_cached_rent() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    assert_eq!(
+        env.client()
+            .minimum_balance_for_rent_exemption(100)
+            .unwrap(),
+        env.rent_exemption_amount(100)
+    );
+}
+
+#[test]
+fn oversized_transaction_is_rejected_before_sending() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let result = env.run_instruction(memo(&"a".repeat(PACKET_DATA_SIZE)), &[]);
+    assert!(matches!(
+        result,
+        Err(ClientError::TransactionTooLarge { size }) if size > PACKET_DATA_SIZE
+    ));
+}
+
+#[test]
+fn builtin_processor_is_registered() {
+    let program_id = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_processor(program_id, processor!(fail_with_seven).unwrap())
+        .build_local_sync();
+
+    let result = env.run_instruction(
+        Instruction::new_with_bytes(program_id, &[], Vec::new()),
+        &[],
+    );
+    assert!(matches!(
+        result,
+        Err(ClientError::FailedTransaction {
+            error: TransactionError::InstructionError(0, InstructionError::Custom(7)),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn token_holders_are_listed() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let authority = Keypair::new();
+    let mint = create_mint(&mut env, &authority);
+    let first = env
+        .mint_to_new_account(mint, &authority, Pubkey::new_unique(), 30)
+        .unwrap();
+    let second = env
+        .mint_to_new_account(mint, &authority, Pubkey::new_unique(), 12)
+        .unwrap();
+    // Accounts of another mint are excluded
+    let other_mint = create_mint(&mut env, &authority);
+    env.mint_to_new_account(other_mint, &authority, Pubkey::new_unique(), 7)
+        .unwrap();
+
+    let mut holders = env.token_holders(mint).unwrap();
+    holders.sort();
+    let mut expected = vec![(first, 30), (second, 12)];
+    expected.sort();
+    assert_eq!(holders, expected);
+}
+
+#[test]
+fn program_is_deployed_with_custom_chunk_size() {
+    let data = memo_program_data();
+    let mut env = EnvironmentGenesis::new()
+        .with_write_chunk_size(500)
+        .build_local_sync();
+    let program = Keypair::new();
+
+    env.deploy_program(&program, &data).unwrap();
+    env.verify_program(program.pubkey(), &data).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "Write chunk size should be between")]
+fn zero_chunk_size_is_rejected() {
+    EnvironmentGenesis::new().with_write_chunk_size(0);
+}
+
+#[test]
+fn prebuilt_transaction_is_signed_and_sent() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    let transaction = Transaction::new_with_payer(
+        &[system_instruction::transfer(
+            &payer,
+            &recipient,
+            sol_to_lamports(1.0),
+        )],
+        Some(&payer),
+    );
+    env.send_prebuilt(transaction, &[]).unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+
+    // The source of the transfer is a required signer that is not provided
+    let stranger = Keypair::new();
+    let transaction = Transaction::new_with_payer(
+        &[system_instruction::transfer(
+            &stranger.pubkey(),
+            &recipient,
+            1,
+        )],
+        Some(&payer),
+    );
+    assert!(matches!(
+        env.send_prebuilt(transaction, &[]),
+        Err(ClientError::InvalidTransaction(
+            TransactionError::SignatureFailure
+        ))
+    ));
+}
+
+#[test]
+fn compute_units_are_accumulated() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    assert_eq!(env.total_units_consumed(), 0);
+
+    let first = env.run_instructions_units(&[memo("first")], &[]).unwrap();
+    let second = env.run_instructions_units(&[memo("second")], &[]).unwrap();
+    assert_eq!(env.total_units_consumed(), first + second);
+
+    env.reset_unit_counter();
+    assert_eq!(env.total_units_consumed(), 0);
+}
+
+#[test]
+fn identical_transaction_is_retried() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+    let instruction = system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0));
+
+    env.run_instruction(instruction.clone(), &[]).unwrap();
+    env.run_instruction(instruction, &[]).unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(2.0)
+    );
+}
+
+#[test]
+fn bank_is_advanced_to_slot_and_epoch() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    env.warp_to_slot(100);
+    assert_eq!(env.slot().unwrap(), 100);
+
+    let epoch_schedule = env.epoch_schedule().unwrap();
+    let next_epoch = epoch_schedule.get_epoch(100) + 1;
+    env.advance_epoch();
+    assert_eq!(
+        env.slot().unwrap(),
+        epoch_schedule.get_first_slot_in_epoch(next_epoch)
+    );
+
+    env.run_instruction(memo("advanced"), &[]).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "Cannot warp to slot")]
+fn warp_to_past_slot_is_rejected() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    env.warp_to_slot(10);
+    env.warp_to_slot(5);
+}
+
+#[test]
+fn program_upgrade_authority_is_read() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let data = memo_program_data();
+    let authority = Pubkey::new_unique();
+    let upgradeable = Pubkey::new_unique();
+    let immutable = Pubkey::new_unique();
+    env.client()
+        .set_upgradeable_program(upgradeable, Some(authority), &data, 0);
+    env.client()
+        .set_upgradeable_program(immutable, None, &data, 0);
+
+    assert_eq!(
+        env.program_upgrade_authority(upgradeable).unwrap(),
+        Some(authority)
+    );
+    assert_eq!(env.program_upgrade_authority(immutable).unwrap(), None);
+    // Programs of the non-upgradeable loader do not have an upgrade authority
+    assert!(matches!(
+        env.program_upgrade_authority(MEMO_PROGRAM_ID),
+        Err(ClientError::InvalidAccountData(address)) if address == MEMO_PROGRAM_ID
+    ));
+}
+
+#[test]
+fn account_is_assigned_to_a_new_owner() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let account = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    env.run_instruction(
+        system_instruction::transfer(&payer, &account.pubkey(), sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    env.assign_account(&account, owner).unwrap();
+    assert_eq!(env.get_account(account.pubkey()).unwrap().owner, owner);
+}
+
+#[test]
+fn transaction_script_is_replayed() {
+    let path = temp_dir().join(format!("sunburn-script-{}.jsonl", Pubkey::new_unique()));
+    let recipient = Pubkey::new_unique();
+    let genesis = || {
+        EnvironmentGenesis::new().add_payer(EnvironmentGenesis::keypair_from_seed(b"script payer"))
+    };
+
+    {
+        let mut env = genesis().with_transaction_script(&path).build_local_sync();
+        let payer = env.payer().pubkey();
+        env.run_instruction(
+            system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+            &[],
+        )
+        .unwrap();
+        env.run_instruction(memo("recorded"), &[]).unwrap();
+    }
+
+    // The signers are recorded with their labels
+    let script = fs::read_to_string(&path).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(script.lines().next().unwrap()).unwrap();
+    assert_eq!(entry["signers"], serde_json::json!(["Payer"]));
+
+    let mut env = genesis().build_local_sync();
+    let results = env.replay_script(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn account_is_created_and_initialized_together() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let account = Keypair::new();
+    let owner = Pubkey::new_unique();
+
+    let initialize = spl_token::instruction::initialize_account(
+        &spl_token::ID,
+        &account.pubkey(),
+        &mint,
+        &owner,
+    )
+    .unwrap();
+    env.create_and_init(
+        &account,
+        spl_token::ID,
+        spl_token::state::Account::LEN,
+        initialize,
+    )
+    .unwrap();
+    assert_eq!(env.token_account(account.pubkey()).unwrap().owner, owner);
+}
+
+#[test]
+fn mint_authorities_are_changed() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = Keypair::new();
+    let authority = Keypair::new();
+    let freezer = Keypair::new();
+    env.create_token_mint(&mint, authority.pubkey(), Some(freezer.pubkey()), 0)
+        .unwrap();
+    let mint = mint.pubkey();
+
+    assert_eq!(env.mint_authority(mint).unwrap(), Some(authority.pubkey()));
+    assert_eq!(
+        env.mint_freeze_authority(mint).unwrap(),
+        Some(freezer.pubkey())
+    );
+
+    env.set_mint_authority(mint, &authority, None).unwrap();
+    env.set_freeze_authority(mint, &freezer, None).unwrap();
+    assert_eq!(env.mint_authority(mint).unwrap(), None);
+    assert_eq!(env.mint_freeze_authority(mint).unwrap(), None);
+}
+
+#[test]
+fn template_builds_independent_environments() {
+    let template = EnvironmentGenesis::new().build_local_template();
+    let mut first = template.build();
+    let mut second = template.build();
+    assert_eq!(first.payer().pubkey(), second.payer().pubkey());
+
+    let payer = first.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+    first
+        .run_instruction(
+            system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        first.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+    assert!(matches!(
+        second.get_account(recipient),
+        Err(ClientError::AccountNotFound(_))
+    ));
+}
+
+#[test]
+fn program_deployed_slot_is_read() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let program_id = Pubkey::new_unique();
+    env.client()
+        .set_upgradeable_program(program_id, None, &memo_program_data(), 5);
+
+    assert_eq!(env.program_deployed_slot(program_id).unwrap(), 5);
+}
+
+#[test]
+fn extra_accounts_are_appended() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let cosigner = Keypair::new();
+
+    env.run_instruction_with_extra_accounts(
+        memo("cosigned"),
+        &[AccountMeta::new_readonly(cosigner.pubkey(), true)],
+        &[&cosigner],
+    )
+    .unwrap();
+
+    // The memo program requires every account to be a signer
+    let result = env.run_instruction_with_extra_accounts(
+        memo("unsigned"),
+        &[AccountMeta::new_readonly(cosigner.pubkey(), false)],
+        &[],
+    );
+    assert!(matches!(
+        result,
+        Err(ClientError::FailedTransaction {
+            error: TransactionError::InstructionError(
+                0,
+                InstructionError::MissingRequiredSignature
+            ),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn verbose_logs_keep_the_run_summary_enabled() {
+    let recipient = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_address_label("Recipient", recipient)
+        .setup_logging(LogConfig {
+            log_summary_on_drop: true,
+            ..LogConfig::basic()
+        })
+        .build_local_sync();
+    let payer = env.payer().pubkey();
+
+    env.with_verbose_logs(|env| {
+        env.run_instruction(
+            system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+            &[],
+        )
+    })
+    .unwrap();
+
+    assert_eq!(
+        env.run_summary().lamport_changes,
+        vec![("Recipient".to_string(), 1_000_000_000)]
+    );
+}
+
+#[test]
+fn run_summary_reports_labeled_balance_changes() {
+    let recipient = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_address_label("Recipient", recipient)
+        .setup_logging(LogConfig {
+            log_summary_on_drop: true,
+            ..LogConfig::basic()
+        })
+        .build_local_sync();
+    let payer = env.payer().pubkey();
+
+    env.run_instruction(
+        system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+
+    let summary = env.run_summary();
+    assert_eq!(summary.transactions_sent, 1);
+    assert_eq!(
+        summary.lamport_changes,
+        vec![("Recipient".to_string(), 1_000_000_000)]
+    );
+    assert_eq!(
+        summary.to_string(),
+        format!(
+            "Transactions sent: 1\nCompute units consumed: {}\nRecipient: +1000000000 lamports",
+            summary.total_units_consumed
+        )
+    );
+}
+
+#[test]
+fn separate_fee_payer_pays_the_fees() {
+    let mut env = EnvironmentGenesis::new().with_fees(true).build_local_sync();
+    let payer = env.payer().pubkey();
+    let sponsor = Keypair::new();
+    let owner = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    env.run_instructions(
+        &[
+            system_instruction::transfer(&payer, &sponsor.pubkey(), sol_to_lamports(10.0)),
+            system_instruction::transfer(&payer, &owner.pubkey(), sol_to_lamports(10.0)),
+        ],
+        &[],
+    )
+    .unwrap();
+
+    env.run_instruction_with_payer(
+        system_instruction::transfer(&owner.pubkey(), &recipient, sol_to_lamports(1.0)),
+        &sponsor,
+        &[&owner],
+    )
+    .unwrap();
+
+    // The sponsor pays for both signatures
+    assert_eq!(
+        env.get_account(sponsor.pubkey()).unwrap().lamports,
+        sol_to_lamports(10.0) - 20_000
+    );
+    assert_eq!(
+        env.get_account(owner.pubkey()).unwrap().lamports,
+        sol_to_lamports(9.0)
+    );
+}
+
+#[test]
+fn rent_collected_during_transaction_is_reported() {
+    let rent_payer = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            rent_payer,
+            AccountConfig {
+                lamports: Some(1_000_000),
+                data: vec![0; 100_000],
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+    let payer = env.payer().pubkey();
+
+    // Rent is collected from the writable accounts of a transaction
+    let details = execute(
+        &mut env,
+        &[system_instruction::transfer(&payer, &rent_payer, 1)],
+    )
+    .unwrap();
+    let collected = details.rent_collected_from(rent_payer);
+    assert!(collected > 0);
+    assert_eq!(
+        env.get_account(rent_payer).unwrap().lamports,
+        1_000_000 + 1 - collected
+    );
+}
+
+#[test]
+fn fees_stay_disabled_after_warp() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    env.warp_to_slot(100);
+    env.new_blockhash().unwrap();
+
+    let before = env.payer_balance().unwrap();
+    env.run_instruction(memo("free"), &[]).unwrap();
+    assert_eq!(env.payer_balance().unwrap(), before);
+}
+
+#[test]
+fn fees_stay_disabled_with_initial_slot() {
+    let mut env = EnvironmentGenesis::new()
+        .with_initial_slot(100)
+        .build_local_sync();
+
+    let before = env.payer_balance().unwrap();
+    env.run_instruction(memo("free"), &[]).unwrap();
+    assert_eq!(env.payer_balance().unwrap(), before);
+}
+
+#[test]
+fn warp_records_every_epoch_transition() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let epoch_schedule = env.epoch_schedule().unwrap();
+    let slot = epoch_schedule.get_first_slot_in_epoch(3) + 1;
+
+    env.warp_to_slot(slot);
+    assert_eq!(env.slot().unwrap(), slot);
+
+    let stake_history = env.stake_history().unwrap();
+    for epoch in 0..3 {
+        assert!(
+            stake_history
+                .iter()
+                .any(|(entry_epoch, _)| *entry_epoch == epoch),
+            "StakeHistory is missing epoch {}",
+            epoch
+        );
+    }
+}
+
+#[test]
+fn realloc_rejects_self_funding() {
+    let funder = EnvironmentGenesis::keypair_from_seed(b"funder");
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            funder.pubkey(),
+            AccountConfig {
+                lamports: Some(sol_to_lamports(10.0)),
+                ..Default::default()
+            },
+        )
+        .build_local_sync();
+
+    let result = env.realloc_account(funder.pubkey(), 100, &funder);
+    assert!(matches!(
+        result,
+        Err(ClientError::SelfFundedReallocation(address)) if address == funder.pubkey()
+    ));
+}
+
+#[test]
+fn json_dump_round_trip() {
+    let address = Pubkey::new_unique();
+    let mut env = EnvironmentGenesis::new()
+        .add_account(
+            address,
+            AccountConfig {
+                lamports: Some(sol_to_lamports(1.0)),
+                data: vec![0, 1, 2, 3],
+                owner: Pubkey::new_unique(),
+                executable: false,
+                rent_epoch: Some(7),
+            },
+        )
+        .build_local_sync();
+    let original = env.get_account(address).unwrap();
+
+    let dir = temp_dir().join(format!("sunburn-json-round-trip-{}", address));
+    env.client().dump_accounts_to_dir(&[address], &dir).unwrap();
+
+    let mut loaded = EnvironmentGenesis::new()
+        .add_accounts_from_json(&dir)
+        .build_local_sync();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(loaded.get_account(address).unwrap(), original);
+}
+
+#[test]
+fn local_client_verifies_signatures() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let blockhash = env.new_blockhash().unwrap();
+
+    // The signature of the payer is left empty
+    let mut transaction = Transaction::new_with_payer(&[memo("unsigned")], Some(&payer));
+    transaction.message.recent_blockhash = blockhash;
+
+    let result = env.send_raw_transaction(transaction);
+    assert!(matches!(
+        result,
+        Err(ClientError::InvalidTransaction(
+            TransactionError::SignatureFailure
+        ))
+    ));
+}
+
+#[test]
+fn every_send_path_is_counted() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let payer = env.payer().pubkey();
+    let sponsor = EnvironmentGenesis::keypair_from_seed(b"sponsor");
+    env.run_instruction(
+        system_instruction::transfer(&payer, &sponsor.pubkey(), sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+
+    env.run_instruction_with_payer(memo("sponsored"), &sponsor, &[])
+        .unwrap();
+    assert_eq!(env.run_summary().transactions_sent, 2);
+}
+
+#[test]
+fn batched_writes_are_counted() {
+    let data = memo_program_data();
+    let mut env = EnvironmentGenesis::new()
+        .with_batched_writes(true)
+        .with_write_chunk_size(900)
+        .build_local_sync();
+
+    env.deploy_program(&Keypair::new(), &data).unwrap();
+    // Creating the account and finalizing it take one transaction each
+    assert_eq!(
+        env.run_summary().transactions_sent,
+        data.chunks(900).count() as u64 + 2
+    );
+}
+
+#[test]
+fn token_balance_of_another_mint_is_not_set() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let other_mint = create_mint(&mut env, &Keypair::new());
+    let owner = Pubkey::new_unique();
+    let account = get_associated_token_address(&owner, &mint);
+    env.set_token_balance(account, mint, owner, 50).unwrap();
+
+    assert!(matches!(
+        env.set_token_balance(account, other_mint, owner, 10),
+        Err(ClientError::AccountDataMismatch(address)) if address == account
+    ));
+    assert_eq!(env.mint_supply(mint).unwrap(), 50);
+    assert_eq!(env.mint_supply(other_mint).unwrap(), 0);
+}
+
+#[test]
+fn associated_token_accounts_are_split_into_transactions() {
+    let mut env = EnvironmentGenesis::new().build_local_sync();
+    let mint = create_mint(&mut env, &Keypair::new());
+    let pairs: Vec<_> = (0..5).map(|_| (Pubkey::new_unique(), mint)).collect();
+
+    let before = env.run_summary().transactions_sent;
+    let accounts = env.create_associated_token_accounts(&pairs).unwrap();
+    // At most four accounts are created in a transaction
+    assert_eq!(env.run_summary().transactions_sent - before, 2);
+    for account in accounts {
+        assert_eq!(env.token_balance(account).unwrap(), 0);
+    }
+}
@@ -0,0 +1,15 @@
+use solana_client::rpc_client::RpcClient;
+use sunburn::{client::ClientError, EnvironmentGenesis};
+
+#[test]
+fn remote_environment_requires_payer() {
+    let result = EnvironmentGenesis::new().build_remote_sync("http://127.0.0.1:1");
+    assert!(matches!(result, Err(ClientError::PayerNotSpecified)));
+}
+
+#[test]
+fn remote_environment_with_custom_client_requires_payer() {
+    let client = RpcClient::new("http://127.0.0.1:1".to_string());
+    let result = EnvironmentGenesis::new().build_remote_sync_with_client(client);
+    assert!(matches!(result, Err(ClientError::PayerNotSpecified)));
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "test-validator")]
+
+use solana_program_test::processor;
+use solana_sdk::{
+    account_info::AccountInfo, entrypoint::ProgramResult, native_token::sol_to_lamports,
+    pubkey::Pubkey, signer::Signer, system_instruction,
+};
+use sunburn::{client::ClientError, EnvironmentGenesis, Loader};
+
+fn noop(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+#[test]
+fn transfer_on_test_validator() {
+    let mut env = EnvironmentGenesis::new()
+        .build_test_validator_sync()
+        .unwrap();
+    let payer = env.payer().pubkey();
+    let recipient = Pubkey::new_unique();
+
+    env.run_instruction(
+        system_instruction::transfer(&payer, &recipient, sol_to_lamports(1.0)),
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        env.get_account(recipient).unwrap().lamports,
+        sol_to_lamports(1.0)
+    );
+}
+
+#[test]
+fn builtin_loader_is_not_supported() {
+    let loader: Loader = (
+        "noop".to_string(),
+        Pubkey::new_unique(),
+        processor!(noop).unwrap(),
+    );
+    let result = EnvironmentGenesis::new()
+        .add_loader(loader)
+        .build_test_validator_sync();
+    assert!(matches!(result, Err(ClientError::UnsupportedOption(_))));
+}